@@ -0,0 +1,360 @@
+//! The read-only HTTP API explorers use to show verification status.  Every response apart from svg badges is json:
+//!
+//!   GET /programs/{cluster}/{address}   the program's record, staleness, security.txt summary and history
+//!   GET /repos?url={git repo}           every record verified from a repo
+//!   GET /badges/{cluster}/{address}.svg    the program's status badge
+//!   GET /badges/{cluster}/{address}.json   the same badge as a shields.io endpoint
+//!
+//! Errors are {"error": ...} with a 4xx or 5xx status.  Records and history are read from the chain, or from a local
+//! index kept up to date with `index sync`.  Nothing here depends on the HTTP server, which only passes requests in
+
+use crate::state::{Result, Network, ProgramMetaData, network_to_string};
+use crate::client::{VerifierClient, fetch_deployment, fetch_security_report, staleness};
+use crate::index::{VerificationIndex, RecordQuery};
+use crate::registry::{RecordEntry, RecordEvent};
+use crate::security::SecuritySummary;
+use crate::badge::{Badge, BadgeFormat, verification_time};
+
+use serde::Serialize;
+use solana_program::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Where records and history are read from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiBackend {
+    Chain,
+    // path of the index database
+    Index(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiResponse {
+    pub status : u16,
+    pub content_type : &'static str,
+    pub body : String
+}
+
+impl ApiResponse {
+    fn json<T : Serialize>(status : u16, value : &T) -> ApiResponse {
+        match serde_json::to_string_pretty(value) {
+            Ok(body) => ApiResponse {status : status, content_type : "application/json", body : body},
+            Err(err) => ApiResponse::error(500, &err.to_string())
+        }
+    }
+
+    fn error(status : u16, error : &str) -> ApiResponse {
+        ApiResponse {status : status, content_type : "application/json", body : serde_json::json!({"error" : error}).to_string()}
+    }
+}
+
+// the network and program named in a path, or the response explaining why they aren't valid
+fn parse_program(cluster : &str, address : &str) -> std::result::Result<(Network, Pubkey), ApiResponse> {
+    let network = Network::from_str(cluster).map_err(|_| ApiResponse::error(400, &format!("unknown cluster {}", cluster)))?;
+    let program = Pubkey::from_str(address).map_err(|_| ApiResponse::error(400, &format!("{} is not a program address", address)))?;
+    Ok((network, program))
+}
+
+/// The response to GET /programs/{cluster}/{address}
+#[derive(Serialize, Debug, Clone)]
+pub struct ProgramResponse {
+    // chain or index
+    pub backend : String,
+    pub record : RecordEntry,
+    // how the program is deployed now, None if it couldn't be read
+    pub upgradeable : Option<bool>,
+    pub upgrade_authority : Option<String>,
+    pub last_deploy_slot : Option<u64>,
+    pub security_txt : Option<SecuritySummary>,
+    // submissions and verifications, oldest first
+    pub history : Vec<RecordEvent>
+}
+
+/// The response to GET /repos?url=
+#[derive(Serialize, Debug, Clone)]
+pub struct RepoResponse {
+    pub backend : String,
+    pub url : String,
+    pub records : Vec<RecordEntry>
+}
+
+// repo urls are compared without a trailing slash or .git, and ignoring case as the common hosts do
+fn same_repo(a : &str, b : &str) -> bool {
+    let normalise = |url : &str| url.trim().trim_end_matches('/').trim_end_matches(".git").to_ascii_lowercase();
+    normalise(a) == normalise(b)
+}
+
+pub struct ApiServer {
+    client : VerifierClient,
+    backend : ApiBackend,
+    // how many of a record's most recent transactions its history is read from, when reading from the chain
+    history_limit : usize,
+    cache_ttl : Duration,
+    cache : Mutex<HashMap<String, (Instant, ApiResponse)>>,
+    // the last scan of every record on chain, which repo lookups are answered from while it is within the cache ttl
+    scan : tokio::sync::Mutex<Option<(Instant, Vec<RecordEntry>)>>
+}
+
+impl ApiServer {
+
+    pub fn new(client : VerifierClient, backend : ApiBackend, history_limit : usize, cache_ttl : Duration) -> ApiServer {
+        ApiServer {
+            client : client,
+            backend : backend,
+            history_limit : history_limit,
+            cache_ttl : cache_ttl,
+            cache : Mutex::new(HashMap::new()),
+            scan : tokio::sync::Mutex::new(None)
+        }
+    }
+
+    fn backend_name(&self) -> String {
+        match self.backend {
+            ApiBackend::Chain => "chain",
+            ApiBackend::Index(_) => "index"
+        }.to_string()
+    }
+
+    /// Answers a request, from the cache if the same request was answered within the cache ttl
+    pub async fn handle(&self, method : &str, path : &str, query : Option<&str>) -> ApiResponse {
+
+        if method != "GET" {
+            return ApiResponse::error(405, "only GET is supported");
+        }
+
+        let key = format!("{}?{}", path, query.unwrap_or_default());
+        if let Some((cached_at, response)) = self.cache.lock().unwrap().get(&key) {
+            if cached_at.elapsed() < self.cache_ttl {
+                return response.clone();
+            }
+        }
+
+        let response = self.route(path, query).await;
+
+        // errors from the rpc may be transient, so only answers are cached.  Repo lookups are answered from the cached scan,
+        // so caching them too would only let each new url grow the cache
+        if (response.status == 200 || response.status == 404) && path.trim_matches('/') != "repos" {
+            let mut cache = self.cache.lock().unwrap();
+            cache.retain(|_, (cached_at, _)| cached_at.elapsed() < self.cache_ttl);
+            cache.insert(key, (Instant::now(), response.clone()));
+        }
+
+        response
+    }
+
+    async fn route(&self, path : &str, query : Option<&str>) -> ApiResponse {
+
+        let segments : Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        match segments.as_slice() {
+            ["programs", cluster, address] => {
+                let (network, program) = match parse_program(cluster, address) {
+                    Ok(parsed) => parsed,
+                    Err(response) => return response
+                };
+                match self.program(&program, network).await {
+                    Ok(Some(response)) => ApiResponse::json(200, &response),
+                    Ok(None) => ApiResponse::error(404, &format!("{} on {} has no verification record", program, network_to_string(network))),
+                    Err(err) => ApiResponse::error(502, &err.to_string())
+                }
+            },
+            ["repos"] => {
+                let url = query.and_then(|query| url::form_urlencoded::parse(query.as_bytes()).find(|(key, _)| key == "url").map(|(_, url)| url.to_string()));
+                match url {
+                    Some(url) if !url.trim().is_empty() => match self.repo(&url).await {
+                        Ok(response) => ApiResponse::json(200, &response),
+                        Err(err) => ApiResponse::error(502, &err.to_string())
+                    },
+                    _ => ApiResponse::error(400, "the url of a git repo is required, as ?url=")
+                }
+            },
+            ["badges", cluster, file] => {
+                let (address, format) = match (file.strip_suffix(".svg"), file.strip_suffix(".json")) {
+                    (Some(address), _) => (address, BadgeFormat::Svg),
+                    (_, Some(address)) => (address, BadgeFormat::Shields),
+                    _ => return ApiResponse::error(404, "badges are .svg or .json")
+                };
+                let (network, program) = match parse_program(cluster, address) {
+                    Ok(parsed) => parsed,
+                    Err(response) => return response
+                };
+                // a program without a record still gets a badge, saying it's unverified
+                match self.badge(&program, network).await.and_then(|badge| badge.render(format)) {
+                    Ok(body) => ApiResponse {
+                        status : 200,
+                        content_type : if format == BadgeFormat::Svg { "image/svg+xml" } else { "application/json" },
+                        body : body
+                    },
+                    Err(err) => ApiResponse::error(502, &err.to_string())
+                }
+            },
+            _ => ApiResponse::error(404, &format!("no route for {}", path))
+        }
+    }
+
+    // a program's record and history from the backend, None if it has no record
+    async fn record(&self, program : &Pubkey, network : Network) -> Result<Option<(RecordEntry, Vec<RecordEvent>)>> {
+
+        match &self.backend {
+            ApiBackend::Chain => {
+                let metadata_key = self.client.metadata_address(program, network);
+                let account = self.client.rpc().get_account_with_commitment(&metadata_key, self.client.rpc().commitment()).await?.value;
+                let record = match account {
+                    Some(account) => ProgramMetaData::unpack(&account.data)?,
+                    None => return Ok(None)
+                };
+                let history = self.client.record_events(program, network, self.history_limit).await?;
+                Ok(Some((RecordEntry::new(&metadata_key, Some((*program, network)), &record, None), history)))
+            },
+            ApiBackend::Index(path) => {
+                let index = VerificationIndex::open(path, &self.client.program_id())?;
+                let query = RecordQuery {program : Some(program.to_string()), cluster : Some(network), ..RecordQuery::default()};
+                match index.records(&query)?.into_iter().next() {
+                    Some(record) => Ok(Some((record, index.record_events(program, network)?))),
+                    None => Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Everything known about a program, None if it has no verification record
+    pub async fn program(&self, program : &Pubkey, network : Network) -> Result<Option<ProgramResponse>> {
+
+        let (record, history) = match self.record(program, network).await? {
+            Some(found) => found,
+            None => return Ok(None)
+        };
+
+        // the program itself is always read from its cluster, as neither the record nor the index says how it's deployed now
+        let program_client = self.client.program_client(network);
+        let deployment = fetch_deployment(&program_client, program).await.ok();
+        let security_txt = fetch_security_report(&program_client, program).await.ok().map(|report| SecuritySummary::from(&report));
+
+        let stale = deployment.as_ref().and_then(|deployment| staleness(record.verified_code, record.last_verified_slot, deployment));
+
+        Ok(Some(ProgramResponse {
+            backend : self.backend_name(),
+            record : RecordEntry {stale : stale, ..record},
+            upgradeable : deployment.as_ref().map(|deployment| deployment.upgradeable),
+            upgrade_authority : deployment.as_ref().and_then(|deployment| deployment.upgrade_authority.map(|authority| authority.to_string())),
+            last_deploy_slot : deployment.as_ref().and_then(|deployment| deployment.last_deploy_slot),
+            security_txt : security_txt,
+            history : history
+        }))
+    }
+
+    /// The badge of a program, without its security.txt as a badge doesn't show it
+    pub async fn badge(&self, program : &Pubkey, network : Network) -> Result<Badge> {
+
+        let (record, history) = match self.record(program, network).await? {
+            Some(found) => found,
+            None => return Ok(Badge::unverified())
+        };
+
+        let deployment = fetch_deployment(&self.client.program_client(network), program).await.ok();
+        let stale = deployment.and_then(|deployment| staleness(record.verified_code, record.last_verified_slot, &deployment));
+
+        Ok(Badge::new(record.verified_code, &record.data_hash, stale, verification_time(&history)))
+    }
+
+    // every record on chain, from the last scan if it is within the cache ttl.  The lock is held while scanning, so
+    // lookups that arrive meanwhile wait for that scan rather than starting their own
+    async fn scanned_records(&self) -> Result<Vec<RecordEntry>> {
+
+        let mut scan = self.scan.lock().await;
+        if let Some((scanned_at, records)) = scan.as_ref() {
+            if scanned_at.elapsed() < self.cache_ttl {
+                return Ok(records.clone());
+            }
+        }
+
+        let records = self.client.scan_records(false).await?;
+        *scan = Some((Instant::now(), records.clone()));
+
+        Ok(records)
+    }
+
+    /// Every record verified from a repo.  Without an index the records come from a scan of every record, which is
+    /// shared by all repo lookups for the cache ttl
+    pub async fn repo(&self, url : &str) -> Result<RepoResponse> {
+
+        let records = match &self.backend {
+            ApiBackend::Chain => self.scanned_records().await?,
+            ApiBackend::Index(path) => VerificationIndex::open(path, &self.client.program_id())?.records(&RecordQuery::default())?
+        };
+
+        Ok(RepoResponse {
+            backend : self.backend_name(),
+            url : url.to_string(),
+            records : records.into_iter().filter(|record| same_repo(&record.git_repo, url)).collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ClusterConfig, PROGRAM_KEY};
+    use solana_client::nonblocking::rpc_client::RpcClient;
+
+    // nothing listens on this port, so a route that reaches the rpc answers 502
+    const UNREACHABLE : &str = "http://127.0.0.1:1";
+
+    fn server() -> ApiServer {
+        let clusters = ClusterConfig {test_net : UNREACHABLE.to_string(), dev_net : UNREACHABLE.to_string(), main_net : UNREACHABLE.to_string()};
+        let client = VerifierClient::new(RpcClient::new(UNREACHABLE.to_string()), Pubkey::from_str(PROGRAM_KEY).unwrap(), clusters);
+        ApiServer::new(client, ApiBackend::Chain, 50, Duration::from_secs(30))
+    }
+
+    fn get(path : &str, query : Option<&str>) -> ApiResponse {
+        tokio::runtime::Runtime::new().unwrap().block_on(server().handle("GET", path, query))
+    }
+
+    #[test]
+    fn programs_are_parsed_from_the_path() {
+        let (network, program) = parse_program("devnet", PROGRAM_KEY).unwrap();
+        assert_eq!(network, Network::DevNet);
+        assert_eq!(program.to_string(), PROGRAM_KEY);
+        assert_eq!(parse_program("main_net", PROGRAM_KEY).unwrap().0, Network::MainNet);
+
+        assert_eq!(parse_program("localnet", PROGRAM_KEY).unwrap_err().status, 400);
+        assert_eq!(parse_program("dev_net", "not-a-key").unwrap_err().status, 400);
+    }
+
+    #[test]
+    fn invalid_programs_are_rejected_before_the_rpc() {
+        assert_eq!(get(&format!("/programs/localnet/{}", PROGRAM_KEY), None).status, 400);
+        assert_eq!(get("/programs/dev_net/not-a-key", None).status, 400);
+        assert_eq!(get(&format!("/badges/localnet/{}.svg", PROGRAM_KEY), None).status, 400);
+        assert_eq!(get("/badges/dev_net/not-a-key.json", None).status, 400);
+    }
+
+    #[test]
+    fn badges_are_svg_or_json() {
+        assert_eq!(get(&format!("/badges/dev_net/{}.png", PROGRAM_KEY), None).status, 404);
+        assert_eq!(get(&format!("/badges/dev_net/{}", PROGRAM_KEY), None).status, 404);
+
+        // both formats route to the rpc, which isn't there
+        assert_eq!(get(&format!("/badges/dev_net/{}.svg", PROGRAM_KEY), None).status, 502);
+        assert_eq!(get(&format!("/badges/dev_net/{}.json", PROGRAM_KEY), None).status, 502);
+    }
+
+    #[test]
+    fn other_requests_are_refused() {
+        assert_eq!(get("/repos", None).status, 400);
+        assert_eq!(get("/repos", Some("url=")).status, 400);
+        assert_eq!(get("/programs/dev_net", None).status, 404);
+        assert_eq!(get("/", None).status, 404);
+
+        let response = tokio::runtime::Runtime::new().unwrap().block_on(server().handle("POST", "/repos", Some("url=x")));
+        assert_eq!(response.status, 405);
+    }
+
+    #[test]
+    fn repos_are_compared_loosely() {
+        assert!(same_repo("https://github.com/daoplays/sol_verify", "https://github.com/DaoPlays/sol_verify.git/"));
+        assert!(!same_repo("https://github.com/daoplays/sol_verify", "https://github.com/daoplays/sol_verify_api"));
+    }
+}
@@ -0,0 +1,233 @@
+//! Status badges for program READMEs, as an svg or as the json a shields.io endpoint badge reads
+
+use crate::state::{self, Result, Network, ProgramMetaData};
+use crate::client::{VerifierClient, fetch_deployment, staleness};
+use crate::registry::RecordEvent;
+use crate::hash::to_hex;
+
+use clap::ValueEnum;
+use serde::Serialize;
+use solana_program::pubkey::Pubkey;
+
+pub const BADGE_LABEL : &str = "sol_verify";
+
+// how many of a record's most recent transactions are searched for when it was last verified
+const BADGE_HISTORY_LIMIT : usize = 20;
+// characters of the verified hash shown on a badge
+const SHORT_HASH_LEN : usize = 8;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum BadgeFormat {
+    Svg,
+    Shields
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BadgeStatus {
+    VerifiedImmutable,
+    VerifiedUpgradeable,
+    Mismatch,
+    Stale,
+    Unverified
+}
+
+impl BadgeStatus {
+    /// The status of a record, where stale is whether the program was upgraded after it was verified
+    pub fn new(verified_code : u8, stale : Option<bool>) -> BadgeStatus {
+        match (verified_code, stale) {
+            (state::MISMATCH, _) => BadgeStatus::Mismatch,
+            (state::MATCH | state::MATCH_BUT_UPGRADEABLE, Some(true)) => BadgeStatus::Stale,
+            (state::MATCH, _) => BadgeStatus::VerifiedImmutable,
+            (state::MATCH_BUT_UPGRADEABLE, _) => BadgeStatus::VerifiedUpgradeable,
+            _ => BadgeStatus::Unverified
+        }
+    }
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            BadgeStatus::VerifiedImmutable => "verified immutable",
+            BadgeStatus::VerifiedUpgradeable => "verified upgradeable",
+            BadgeStatus::Mismatch => "mismatch",
+            BadgeStatus::Stale => "stale",
+            BadgeStatus::Unverified => "unverified"
+        }
+    }
+
+    // the svg fill, and the named colour shields uses for it
+    fn colours(&self) -> (&'static str, &'static str) {
+        match self {
+            BadgeStatus::VerifiedImmutable => ("#4c1", "brightgreen"),
+            BadgeStatus::VerifiedUpgradeable => ("#a4a61d", "yellowgreen"),
+            BadgeStatus::Mismatch => ("#e05d44", "red"),
+            BadgeStatus::Stale => ("#fe7d37", "orange"),
+            BadgeStatus::Unverified => ("#9f9f9f", "lightgrey")
+        }
+    }
+}
+
+/// The json a shields.io endpoint badge reads
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ShieldsEndpoint {
+    #[serde(rename = "schemaVersion")]
+    pub schema_version : u8,
+    pub label : String,
+    pub message : String,
+    pub color : String
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Badge {
+    pub status : BadgeStatus,
+    // the start of the verified hash, None if the program was never verified
+    pub short_hash : Option<String>,
+    // unix seconds of the last verification, None if it isn't known
+    pub verified_at : Option<i64>
+}
+
+/// YYYY-MM-DD of unix seconds, using the civil from days algorithm
+pub fn date(unix_seconds : i64) -> String {
+    let days = unix_seconds.div_euclid(86400) + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn escape_xml(text : &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// roughly how wide text is in 11px Verdana, plus padding
+fn text_width(text : &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+/// When a program was last verified, from its record's history
+pub fn verification_time(history : &[RecordEvent]) -> Option<i64> {
+    history.iter().rev().find_map(|event| match event {
+        RecordEvent::Verification {block_time, ..} => *block_time,
+        _ => None
+    })
+}
+
+impl Badge {
+    pub fn new(verified_code : u8, data_hash : &str, stale : Option<bool>, verified_at : Option<i64>) -> Badge {
+        let status = BadgeStatus::new(verified_code, stale);
+        Badge {
+            status : status,
+            short_hash : if status == BadgeStatus::Unverified { None } else { Some(data_hash.chars().take(SHORT_HASH_LEN).collect()) },
+            verified_at : if status == BadgeStatus::Unverified { None } else { verified_at }
+        }
+    }
+
+    pub fn from_record(record : &ProgramMetaData, stale : Option<bool>, verified_at : Option<i64>) -> Badge {
+        Badge::new(record.verified_code, &to_hex(&record.data_hash), stale, verified_at)
+    }
+
+    /// The badge of a program with no verification record
+    pub fn unverified() -> Badge {
+        Badge {status : BadgeStatus::Unverified, short_hash : None, verified_at : None}
+    }
+
+    pub fn message(&self) -> String {
+        let mut parts = vec![self.status.text().to_string()];
+        parts.extend(self.short_hash.clone());
+        parts.extend(self.verified_at.map(date));
+        parts.join(" | ")
+    }
+
+    pub fn shields(&self) -> ShieldsEndpoint {
+        ShieldsEndpoint {schema_version : 1, label : BADGE_LABEL.to_string(), message : self.message(), color : self.status.colours().1.to_string()}
+    }
+
+    /// A flat badge in the style of shields.io
+    pub fn svg(&self) -> String {
+
+        let label = escape_xml(BADGE_LABEL);
+        let message = escape_xml(&self.message());
+        let label_width = text_width(BADGE_LABEL);
+        let message_width = text_width(&self.message());
+        let width = label_width + message_width;
+        let label_x = label_width as f64 / 2.0;
+        let message_x = label_width as f64 + message_width as f64 / 2.0;
+
+        format!(concat!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">"##,
+            r##"<title>{label}: {message}</title>"##,
+            r##"<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>"##,
+            r##"<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>"##,
+            r##"<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{fill}"/>"##,
+            r##"<rect width="{width}" height="20" fill="url(#s)"/></g>"##,
+            r##"<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">"##,
+            r##"<text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text><text x="{label_x}" y="14">{label}</text>"##,
+            r##"<text x="{message_x}" y="15" fill="#010101" fill-opacity=".3">{message}</text><text x="{message_x}" y="14">{message}</text>"##,
+            r##"</g></svg>"##, "\n"),
+            width = width, label = label, message = message, label_width = label_width, message_width = message_width,
+            fill = self.status.colours().0, label_x = label_x, message_x = message_x)
+    }
+
+    pub fn render(&self, format : BadgeFormat) -> Result<String> {
+        match format {
+            BadgeFormat::Svg => Ok(self.svg()),
+            BadgeFormat::Shields => Ok(serde_json::to_string_pretty(&self.shields())? + "\n")
+        }
+    }
+}
+
+impl VerifierClient {
+
+    /// The badge of a program, read from its record and the cluster it's deployed to
+    pub async fn badge(&self, program : &Pubkey, network : Network) -> Result<Badge> {
+
+        let metadata_key = self.metadata_address(program, network);
+        let record = match self.rpc().get_account_with_commitment(&metadata_key, self.rpc().commitment()).await?.value {
+            Some(account) => ProgramMetaData::unpack(&account.data)?,
+            None => return Ok(Badge::unverified())
+        };
+
+        let deployment = fetch_deployment(&self.program_client(network), program).await.ok();
+        let stale = deployment.and_then(|deployment| staleness(record.verified_code, record.last_verified_slot, &deployment));
+        let history = self.record_events(program, network, BADGE_HISTORY_LIMIT).await?;
+
+        Ok(Badge::from_record(&record, stale, verification_time(&history)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_are_calendar_days() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(86399), "1970-01-01");
+        assert_eq!(date(951782400), "2000-02-29");
+        assert_eq!(date(1709251199), "2024-02-29");
+        assert_eq!(date(1709251200), "2024-03-01");
+        assert_eq!(date(-1), "1969-12-31");
+    }
+
+    #[test]
+    fn badges_follow_the_record() {
+        assert_eq!(Badge::new(state::MATCH, "abcdef0123456789", Some(false), Some(0)).message(), "verified immutable | abcdef01 | 1970-01-01");
+        assert_eq!(Badge::new(state::MATCH_BUT_UPGRADEABLE, "abcdef0123456789", Some(true), None).message(), "stale | abcdef01");
+        assert_eq!(Badge::new(state::UNINITIALISED, "abcdef0123456789", None, Some(0)), Badge::unverified());
+    }
+
+    #[test]
+    fn svg_text_is_escaped() {
+        let badge = Badge {status : BadgeStatus::Mismatch, short_hash : Some("<a & \"b\">".to_string()), verified_at : None};
+        let svg = badge.svg();
+
+        assert!(svg.contains("mismatch | &lt;a &amp; &quot;b&quot;&gt;"));
+        assert!(!svg.contains("<a "));
+        assert!(!svg.contains("\"b\""));
+        assert_eq!(escape_xml("&amp;"), "&amp;amp;");
+    }
+}
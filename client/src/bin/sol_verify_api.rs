@@ -0,0 +1,106 @@
+use sol_verify_client::api::{ApiServer, ApiBackend};
+use sol_verify_client::client::{VerifierClient, ClusterConfig, PROGRAM_KEY, normalize_to_url_if_moniker};
+
+use clap::Parser;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Serves verification records to explorers over a read-only HTTP API.  The routes are described in the
+/// sol_verify_client api module
+#[derive(Parser, Debug)]
+#[clap(name = "sol_verify_api", version)]
+struct Args {
+    /// Address to listen on
+    #[clap(long, value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+    bind : SocketAddr,
+
+    /// RPC url of the cluster the sol_verify program is deployed to, or one of the monikers mainnet-beta, testnet, devnet or localhost
+    #[clap(short = 'u', long, value_name = "URL_OR_MONIKER", default_value = "devnet")]
+    url : String,
+
+    /// Address of the sol_verify program
+    #[clap(long, value_name = "ADDRESS", default_value = PROGRAM_KEY)]
+    program_id : Pubkey,
+
+    /// Commitment level to use: processed, confirmed or finalized
+    #[clap(long, value_name = "LEVEL", default_value = "confirmed")]
+    commitment : String,
+
+    /// Read records and history from this index, kept up to date with `index sync`, instead of from the chain
+    #[clap(long, value_name = "PATH")]
+    index : Option<String>,
+
+    /// RPC urls programs on each network are read from.  Set all three to localhost to test against solana-test-validator
+    #[clap(long, value_name = "URL_OR_MONIKER", default_value = "testnet")]
+    test_net_url : String,
+    #[clap(long, value_name = "URL_OR_MONIKER", default_value = "devnet")]
+    dev_net_url : String,
+    #[clap(long, value_name = "URL_OR_MONIKER", default_value = "mainnet-beta")]
+    main_net_url : String,
+
+    /// How long responses are cached for, in seconds
+    #[clap(long, value_name = "SECONDS", default_value = "30")]
+    cache_seconds : u64,
+
+    /// How many of a record's most recent transactions its history is read from, when reading from the chain
+    #[clap(long, value_name = "COUNT", default_value = "50")]
+    history_limit : usize
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    let commitment = match CommitmentConfig::from_str(&args.commitment) {
+        Ok(commitment) => commitment,
+        Err(_) => {
+            eprintln!("error: unknown commitment level {}", args.commitment);
+            std::process::exit(2);
+        }
+    };
+
+    let clusters = ClusterConfig {
+        test_net : normalize_to_url_if_moniker(&args.test_net_url),
+        dev_net : normalize_to_url_if_moniker(&args.dev_net_url),
+        main_net : normalize_to_url_if_moniker(&args.main_net_url)
+    };
+    let client = VerifierClient::new(RpcClient::new_with_commitment(normalize_to_url_if_moniker(&args.url), commitment), args.program_id, clusters);
+
+    let backend = match args.index {
+        Some(path) => ApiBackend::Index(path),
+        None => ApiBackend::Chain
+    };
+    let server = Arc::new(ApiServer::new(client, backend, args.history_limit, Duration::from_secs(args.cache_seconds)));
+
+    let make_service = make_service_fn(move |_| {
+        let server = server.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request : Request<Body>| {
+                let server = server.clone();
+                async move {
+                    let response = server.handle(request.method().as_str(), request.uri().path(), request.uri().query()).await;
+                    Response::builder()
+                        .status(response.status)
+                        .header("content-type", response.content_type)
+                        // explorers call this from the browser
+                        .header("access-control-allow-origin", "*")
+                        .body(Body::from(response.body))
+                }
+            }))
+        }
+    });
+
+    println!("serving on http://{}", args.bind);
+    if let Err(err) = Server::bind(&args.bind).serve(make_service).await {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
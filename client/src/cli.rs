@@ -0,0 +1,405 @@
+use sol_verify_client::state::{Error, Result, Network, VerificationStage};
+use sol_verify_client::manifest::{SubmitManifest, DEFAULT_MANIFEST};
+use sol_verify_client::client::{VerifierClient, ClusterConfig, SendOptions, SOLANA_DEV, PROGRAM_KEY, DEFAULT_SEND_RETRIES, normalize_to_url_if_moniker};
+use sol_verify_client::registry::{ScanAccounts, ExportFormat};
+use sol_verify_client::index::DEFAULT_INDEX_PATH;
+use sol_verify_client::badge::BadgeFormat;
+
+use clap::{Args, Parser, Subcommand};
+pub use sol_verify_client::output::OutputFormat;
+use solana_cli_config::{Config, CONFIG_FILE};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::Signature,
+    signer::keypair::{read_keypair_file, Keypair},
+};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Parser, Debug)]
+#[clap(name = "sol_verify", version, about = "Submit, verify and inspect programs using the sol_verify program")]
+pub struct Cli {
+    #[clap(flatten)]
+    pub config : ConfigArgs,
+
+    #[clap(subcommand)]
+    pub command : Command
+}
+
+/// Connection and signing options.  Anything not given is taken from the Solana CLI config file
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    /// Solana CLI config file to read defaults from
+    #[clap(short = 'C', long, global = true, value_name = "PATH")]
+    pub config : Option<String>,
+
+    /// RPC url, or one of the monikers mainnet-beta, testnet, devnet or localhost
+    #[clap(short = 'u', long, global = true, value_name = "URL_OR_MONIKER")]
+    pub url : Option<String>,
+
+    /// Keypair used to sign and pay for transactions
+    #[clap(short = 'k', long, global = true, value_name = "PATH")]
+    pub keypair : Option<String>,
+
+    /// Address of the sol_verify program
+    #[clap(long, global = true, value_name = "ADDRESS", default_value = PROGRAM_KEY)]
+    pub program_id : Pubkey,
+
+    /// Commitment level to use: processed, confirmed or finalized
+    #[clap(long, global = true, value_name = "LEVEL")]
+    pub commitment : Option<String>,
+
+    /// Print results as text, or as a single json object
+    #[clap(short = 'o', long, global = true, value_enum, default_value = "text")]
+    pub output : OutputFormat
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Submit a program for verification
+    Submit(SubmitArgs),
+
+    /// Compare a deployed test build with the on chain program and record the result (verifier only)
+    Verify {
+        /// Keypair of the test program deployed from the repo
+        test_keypair : String,
+        /// Address of the program being verified
+        address : Pubkey,
+        /// Network the program is deployed to: test_net, dev_net or main_net (or 0, 1, 2)
+        network : Network,
+        /// User that submitted the program
+        user : Pubkey,
+        git_repo : String,
+        git_commit : String,
+        directory : String,
+        #[clap(flatten)]
+        send : SendArgs
+    },
+
+    /// Compare a locally built .so with the program deployed at an address on the --url cluster
+    VerifyLocal {
+        /// Address of the deployed program
+        address : Pubkey,
+        /// Path to the locally built program
+        so_path : String
+    },
+
+    /// Print the canonical hash of a .so file or of the program deployed at an address on the --url cluster
+    Hash {
+        /// Path to a program file, or a program address
+        target : String
+    },
+
+    /// Report which sections and dynamic symbols differ between two programs, each a .so file or a program address
+    Diff {
+        /// The reference program, usually the deployed one
+        expected : String,
+        /// The build being checked against it
+        candidate : String
+    },
+
+    /// Disassemble two programs and show the instructions of each function that differs, side by side
+    DisasmDiff {
+        /// The reference program, usually the deployed one
+        expected : String,
+        /// The build being checked against it
+        candidate : String
+    },
+
+    /// Read the toolchain versions recorded in a program, and suggest the versions to submit it with
+    Toolchain {
+        /// Path to a program file, or a program address
+        target : String
+    },
+
+    /// Show the current status and status history of a user's verification
+    Status {
+        /// User that submitted the program
+        user : Pubkey
+    },
+
+    /// Decode the verification record of a program, and check whether it was upgraded after being verified
+    Show {
+        /// Address of the verified program
+        address : Pubkey,
+        /// Cluster the program is deployed to: test_net, dev_net or main_net, or the usual cluster names
+        #[clap(long, alias = "network", default_value = "dev_net")]
+        cluster : Network
+    },
+
+    /// Set the status of a user's verification (verifier only)
+    UpdateStatus {
+        /// User that submitted the program
+        user : Pubkey,
+        status_code : u8,
+        message : String,
+        /// Pipeline stage, by name or index
+        #[clap(long, default_value = "queued")]
+        stage : VerificationStage,
+        /// Percentage complete
+        #[clap(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+        progress : Option<u8>,
+        #[clap(flatten)]
+        send : SendArgs
+    },
+
+    /// Show and check every field of a program's security.txt
+    SecurityTxt {
+        /// Path to a program file, or a program address
+        target : String,
+        /// Also write the report as json to this file
+        #[clap(long, value_name = "PATH")]
+        json_file : Option<String>
+    },
+
+    /// Pin a repo and commit to the source revision a program on the --url cluster declares in its security.txt, as verify checks it
+    PinSource {
+        /// Address of the program
+        address : Pubkey,
+        /// Git repo, or archive url, the program was submitted with
+        git_repo : String,
+        /// Commit the program was submitted with, if any
+        #[clap(long, default_value = "")]
+        git_commit : String,
+        /// Directory of the program in the repo
+        #[clap(long, default_value = "")]
+        directory : String
+    },
+
+    /// Export every verification record, or every user account, held by the verifier program
+    Scan {
+        /// Which accounts to export
+        #[clap(long, value_enum, default_value = "records")]
+        accounts : ScanAccounts,
+        /// Json lines, one object per account, or csv with a header row
+        #[clap(long, value_enum, default_value = "jsonl")]
+        format : ExportFormat,
+        /// Write the export to this file instead of stdout
+        #[clap(long, value_name = "PATH")]
+        out : Option<String>,
+        /// Don't look up each program on its cluster to check whether it was upgraded after being verified
+        #[clap(long)]
+        skip_staleness : bool
+    },
+
+    /// Show every submission and verification of a program next to its deploys and upgrades
+    History {
+        /// Address of the program
+        address : Pubkey,
+        /// Cluster the program is deployed to: test_net, dev_net or main_net, or the usual cluster names
+        #[clap(long, alias = "network", default_value = "dev_net")]
+        cluster : Network
+    },
+
+    /// Decode the sol_verify instructions, account roles, error and logs of a transaction
+    DecodeTx {
+        signature : Signature
+    },
+
+    /// Write a status badge for a program's README
+    Badge {
+        /// Address of the program
+        address : Pubkey,
+        /// Cluster the program is deployed to: test_net, dev_net or main_net, or the usual cluster names
+        #[clap(long, alias = "network", default_value = "dev_net")]
+        cluster : Network,
+        /// An svg, or the json a shields.io endpoint badge reads
+        #[clap(long, value_enum, default_value = "svg")]
+        format : BadgeFormat,
+        /// File to write the badge to
+        #[clap(long, value_name = "PATH", default_value = "sol_verify_badge.svg")]
+        out : String
+    },
+
+    /// Keep a local SQLite index of the verifier program's transactions and accounts, and query it
+    Index {
+        /// Index database, created if it doesn't exist
+        #[clap(long, value_name = "PATH", default_value = DEFAULT_INDEX_PATH)]
+        db : String,
+        #[clap(subcommand)]
+        action : IndexAction
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum IndexAction {
+    /// Add every transaction since the last sync, and refresh the accounts they touched
+    Sync,
+
+    /// Export the indexed verification records, as of the last sync
+    Records {
+        /// Only records verified from this git repo
+        #[clap(long)]
+        repo : Option<String>,
+        /// Only records for programs on this cluster
+        #[clap(long)]
+        cluster : Option<Network>,
+        /// Only records with this verified code, 3 for immutable programs that matched
+        #[clap(long)]
+        verified_code : Option<u8>,
+        /// Json lines, one object per record, or csv with a header row
+        #[clap(long, value_enum, default_value = "jsonl")]
+        format : ExportFormat,
+        /// Write the export to this file instead of stdout
+        #[clap(long, value_name = "PATH")]
+        out : Option<String>
+    }
+}
+
+/// Details of the program to submit.  Anything not given here is read from the manifest file
+#[derive(Args, Debug)]
+pub struct SubmitArgs {
+    /// Address of the program to verify
+    pub address : Option<String>,
+
+    /// JSON manifest with the same fields as these options, defaults to sol_verify.json if it exists
+    #[clap(long, value_name = "PATH")]
+    pub manifest : Option<String>,
+
+    /// Network the program is deployed to: test_net, dev_net or main_net [default: dev_net]
+    #[clap(long)]
+    pub network : Option<String>,
+
+    /// Git repo to clone, or an archive url to download if no commit is given
+    #[clap(long, value_name = "URL")]
+    pub git_repo : Option<String>,
+
+    #[clap(long, value_name = "HASH")]
+    pub git_commit : Option<String>,
+
+    /// Directory within the repo to build from
+    #[clap(long, value_name = "PATH")]
+    pub directory : Option<String>,
+
+    /// One of the verifier's prebuilt docker images, instead of giving the toolchain versions
+    #[clap(long)]
+    pub docker_version : Option<String>,
+
+    #[clap(long)]
+    pub rust_version : Option<String>,
+
+    #[clap(long)]
+    pub solana_version : Option<String>,
+
+    #[clap(long)]
+    pub anchor_version : Option<String>,
+
+    #[clap(flatten)]
+    pub send : SendArgs
+}
+
+impl SubmitArgs {
+    /// combine the command line with the manifest file, the command line taking precedence
+    pub fn resolve(self) -> Result<SubmitManifest> {
+
+        let manifest = match &self.manifest {
+            Some(path) => SubmitManifest::load(Path::new(path))?,
+            None if Path::new(DEFAULT_MANIFEST).exists() => SubmitManifest::load(Path::new(DEFAULT_MANIFEST))?,
+            None => SubmitManifest::default()
+        };
+
+        let args = SubmitManifest {
+            address : self.address,
+            network : self.network,
+            git_repo : self.git_repo,
+            git_commit : self.git_commit,
+            directory : self.directory,
+            docker_version : self.docker_version,
+            rust_version : self.rust_version,
+            solana_version : self.solana_version,
+            anchor_version : self.anchor_version
+        };
+
+        Ok(args.or(manifest))
+    }
+}
+
+/// How the commands that change state send their transactions
+#[derive(Args, Debug, Clone)]
+pub struct SendArgs {
+    /// Simulate the transactions the command would send and show what they would change, without sending them
+    #[clap(long)]
+    pub dry_run : bool,
+
+    /// Commitment level each transaction has to reach before the command carries on [default: --commitment]
+    #[clap(long, value_name = "LEVEL")]
+    pub confirm_commitment : Option<String>,
+
+    /// How many times a transaction that expires before it is confirmed is sent again, with a new blockhash
+    #[clap(long, value_name = "COUNT", default_value_t = DEFAULT_SEND_RETRIES)]
+    pub max_retries : usize,
+
+    /// Priority fee to pay, in micro lamports per compute unit
+    #[clap(long, value_name = "MICRO_LAMPORTS")]
+    pub compute_unit_price : Option<u64>,
+
+    /// Most compute units each transaction can use
+    #[clap(long, value_name = "UNITS")]
+    pub compute_unit_limit : Option<u32>
+}
+
+impl SendArgs {
+    pub fn resolve(&self) -> Result<SendOptions> {
+
+        let commitment = match &self.confirm_commitment {
+            Some(level) => Some(CommitmentConfig::from_str(level).map_err(|_| Error::InvalidConfig(format!("unknown commitment level {}", level)))?),
+            None => None
+        };
+
+        Ok(SendOptions {
+            commitment : commitment,
+            max_retries : self.max_retries,
+            compute_unit_price : self.compute_unit_price,
+            compute_unit_limit : self.compute_unit_limit
+        })
+    }
+}
+
+/// The settings every command runs with, after combining the command line with the Solana CLI config
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub url : String,
+    pub keypair_path : String,
+    pub program_id : Pubkey,
+    pub commitment : CommitmentConfig,
+    pub output : OutputFormat
+}
+
+impl ClientConfig {
+    pub fn keypair(&self) -> Result<Keypair> {
+        read_keypair_file(&self.keypair_path)
+            .map_err(|err| Error::InvalidConfig(format!("failed to read keypair {}: {}", self.keypair_path, err)))
+    }
+
+    pub fn verifier_client(&self) -> VerifierClient {
+        VerifierClient::new(RpcClient::new_with_commitment(self.url.clone(), self.commitment), self.program_id, ClusterConfig::default())
+    }
+
+    /// A client that sends transactions as the command line says, or only simulates them
+    pub fn sending_client(&self, send : &SendArgs) -> Result<VerifierClient> {
+        Ok(self.verifier_client().with_send_options(send.resolve()?).with_dry_run(send.dry_run))
+    }
+}
+
+impl ConfigArgs {
+    pub fn resolve(&self) -> Result<ClientConfig> {
+
+        // an explicitly given config file has to exist, the default one is optional
+        let config = match (&self.config, CONFIG_FILE.as_ref()) {
+            (Some(path), _) => Config::load(path).map_err(Error::ConfigReadError)?,
+            (None, Some(path)) if std::path::Path::new(path).exists() => Config::load(path).map_err(Error::ConfigReadError)?,
+            _ => Config {json_rpc_url : SOLANA_DEV.to_string(), ..Config::default()}
+        };
+
+        let url = normalize_to_url_if_moniker(self.url.as_deref().unwrap_or(&config.json_rpc_url));
+        let keypair_path = self.keypair.clone().unwrap_or(config.keypair_path);
+
+        let commitment_string = self.commitment.clone().unwrap_or(config.commitment);
+        let commitment = CommitmentConfig::from_str(&commitment_string)
+            .map_err(|_| Error::InvalidConfig(format!("unknown commitment level {}", commitment_string)))?;
+
+        Ok(ClientConfig {url : url, keypair_path : keypair_path, program_id : self.program_id, commitment : commitment, output : self.output})
+    }
+}
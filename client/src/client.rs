@@ -0,0 +1,626 @@
+use crate::state::{self, Error, Result, Network, VerifyInstruction, VerifyProgramMeta, ProgramMetaData, StatusMeta, UserMetaData, VerificationStage, network_to_string};
+use crate::manifest::SubmitManifest;
+use crate::diff::ProgramDiff;
+use crate::security::SecurityReport;
+use crate::revision::pin_source;
+use crate::hash::{canonical_hash, canonical_program_bytes, to_hex};
+use crate::output::{SubmitReport, VerifyReport, ProgramRecordReport, StatusReport, StatusUpdateReport};
+use crate::simulate::Simulation;
+use crate::decode::describe_transaction_error;
+
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::RpcSendTransactionConfig,
+    rpc_response::RpcConfirmedTransactionStatusWithSignature
+};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    signature::Signature,
+    signer::Signer,
+    instruction::{AccountMeta, Instruction},
+    transaction::{Transaction, TransactionError},
+    bpf_loader, bpf_loader_deprecated,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState}
+};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const SOLANA_TEST: &str = "https://api.testnet.solana.com";
+pub const SOLANA_DEV: &str = "https://api.devnet.solana.com";
+pub const SOLANA_MAIN: &str = "https://api.mainnet-beta.solana.com";
+pub const SOLANA_LOCAL: &str = "http://localhost:8899";
+
+// the most signatures the rpc returns in one page
+const SIGNATURE_PAGE_LIMIT : usize = 1000;
+
+pub const PROGRAM_KEY : &str = "CNd6wN4en9Xvbf2e1ubb2YyCkC7J1BbbuhAGhqcdHFbi";
+
+pub const DEFAULT_SEND_RETRIES : usize = 3;
+// how often a sent transaction's status is checked
+const CONFIRM_POLL_INTERVAL : Duration = Duration::from_millis(500);
+
+pub fn normalize_to_url_if_moniker(url_or_moniker : &str) -> String {
+    match url_or_moniker {
+        "m" | "mainnet-beta" => SOLANA_MAIN,
+        "t" | "testnet" => SOLANA_TEST,
+        "d" | "devnet" => SOLANA_DEV,
+        "l" | "localhost" => SOLANA_LOCAL,
+        url => url
+    }.to_string()
+}
+
+/// Where to read programs being verified from, for each network they can be deployed to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterConfig {
+    pub test_net : String,
+    pub dev_net : String,
+    pub main_net : String
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        ClusterConfig {test_net : SOLANA_TEST.to_string(), dev_net : SOLANA_DEV.to_string(), main_net : SOLANA_MAIN.to_string()}
+    }
+}
+
+impl ClusterConfig {
+    pub fn url(&self, network : Network) -> &str {
+        match network {
+            Network::TestNet | Network::Invalid => &self.test_net,
+            Network::DevNet => &self.dev_net,
+            Network::MainNet => &self.main_net
+        }
+    }
+}
+
+/// A test build deployed by the verifier, and the submission it was built for
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyRequest {
+    pub real_address : Pubkey,
+    pub test_address : Pubkey,
+    pub network : Network,
+    pub user : Pubkey,
+    pub git_repo : String,
+    pub git_commit : String,
+    pub directory : String
+}
+
+/// How a program is currently deployed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramDeployment {
+    pub upgradeable : bool,
+    pub upgrade_authority : Option<Pubkey>,
+    // slot of the last deploy or upgrade, only known for upgradeable programs
+    pub last_deploy_slot : Option<u64>
+}
+
+// an upgradeable program as stored in its program data account
+struct UpgradeableProgram {
+    slot : u64,
+    upgrade_authority : Option<Pubkey>,
+    elf : Vec<u8>
+}
+
+/// The bytes a deployed program was loaded from.  Upgradeable programs keep these in a separate ProgramData account
+/// after a header, older loaders keep them in the program account itself
+pub async fn fetch_program_elf(rpc : &RpcClient, address : &Pubkey) -> Result<Vec<u8>> {
+
+    let program_account = rpc.get_account(address).await?;
+
+    if bpf_loader::check_id(&program_account.owner) || bpf_loader_deprecated::check_id(&program_account.owner) {
+        return Ok(program_account.data);
+    }
+
+    if !bpf_loader_upgradeable::check_id(&program_account.owner) {
+        return Err(Error::InvalidProgramAccount(format!("{} is not owned by a bpf loader", address)));
+    }
+
+    Ok(fetch_upgradeable_program(rpc, address).await?.elf)
+}
+
+/// Only upgradeable programs have an authority, and they lose it once they are made immutable
+pub async fn fetch_upgrade_authority(rpc : &RpcClient, address : &Pubkey) -> Result<Option<Pubkey>> {
+
+    let program_account = rpc.get_account(address).await?;
+    if !bpf_loader_upgradeable::check_id(&program_account.owner) {
+        return Ok(None);
+    }
+
+    Ok(fetch_upgradeable_program(rpc, address).await?.upgrade_authority)
+}
+
+/// Whether a program can still be upgraded, and when it last was
+pub async fn fetch_deployment(rpc : &RpcClient, address : &Pubkey) -> Result<ProgramDeployment> {
+
+    let program_account = rpc.get_account(address).await?;
+    if !program_account.executable {
+        return Err(Error::InvalidProgramAccount(format!("{} is not an executable program", address)));
+    }
+    if !bpf_loader_upgradeable::check_id(&program_account.owner) {
+        return Ok(ProgramDeployment {upgradeable : false, upgrade_authority : None, last_deploy_slot : None});
+    }
+
+    let program = fetch_upgradeable_program(rpc, address).await?;
+    Ok(ProgramDeployment {upgradeable : program.upgrade_authority.is_some(), upgrade_authority : program.upgrade_authority, last_deploy_slot : Some(program.slot)})
+}
+
+/// Whether a program was upgraded after it was last verified, None if it has never been verified
+pub fn staleness(verified_code : u8, last_verified_slot : u64, deployment : &ProgramDeployment) -> Option<bool> {
+    // verify treats a program deployed before the last verification as unchanged, so anything else is stale
+    match (verified_code, deployment.last_deploy_slot) {
+        (state::UNINITIALISED, _) => None,
+        (_, Some(slot)) => Some(slot >= last_verified_slot),
+        (_, None) => Some(false)
+    }
+}
+
+/// The security.txt of a program deployed to the cluster rpc is connected to
+pub async fn fetch_security_report(rpc : &RpcClient, program : &Pubkey) -> Result<SecurityReport> {
+
+    let program_data = fetch_program_elf(rpc, program).await?;
+    let upgradeable = fetch_upgrade_authority(rpc, program).await?.is_some();
+
+    Ok(SecurityReport::new(program.to_string(), upgradeable, solana_security_txt::find_and_parse(&program_data)))
+}
+
+/// Every signature of a transaction involving address since until, or since the first if until is None, newest first
+pub async fn fetch_signatures(rpc : &RpcClient, address : &Pubkey, until : Option<Signature>) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let config = GetConfirmedSignaturesForAddress2Config {before : before, until : until, limit : Some(SIGNATURE_PAGE_LIMIT), commitment : None};
+        let page = rpc.get_signatures_for_address_with_config(address, config).await?;
+        let full = page.len() == SIGNATURE_PAGE_LIMIT;
+
+        before = match page.last() {
+            Some(status) => Some(Signature::from_str(&status.signature).map_err(|err| Error::InvalidConfig(err.to_string()))?),
+            None => None
+        };
+        signatures.extend(page);
+
+        if !full {
+            return Ok(signatures);
+        }
+    }
+}
+
+async fn fetch_upgradeable_program(rpc : &RpcClient, address : &Pubkey) -> Result<UpgradeableProgram> {
+
+    let program_account = rpc.get_account(address).await?;
+    if !bpf_loader_upgradeable::check_id(&program_account.owner) {
+        return Err(Error::InvalidProgramAccount(format!("{} is not owned by the bpf_loader_upgradeable program", address)));
+    }
+
+    let programdata_address = match bincode::deserialize(&program_account.data) {
+        Ok(UpgradeableLoaderState::Program {programdata_address}) => programdata_address,
+        _ => return Err(Error::InvalidProgramAccount(format!("{} is not an upgradeable program account", address)))
+    };
+
+    let mut program_data = rpc.get_account_data(&programdata_address).await?;
+
+    let data_offset = UpgradeableLoaderState::programdata_data_offset().unwrap();
+    if program_data.len() < data_offset {
+        return Err(Error::InvalidProgramAccount(format!("program data account {} is too small", programdata_address)));
+    }
+
+    match bincode::deserialize(&program_data[..data_offset]) {
+        Ok(UpgradeableLoaderState::ProgramData {slot, upgrade_authority_address}) => Ok(UpgradeableProgram {
+            slot : slot,
+            upgrade_authority : upgrade_authority_address,
+            elf : program_data.split_off(data_offset)
+        }),
+        _ => Err(Error::InvalidProgramAccount(format!("{} is not a program data account", programdata_address)))
+    }
+}
+
+/// How transactions are sent and confirmed
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendOptions {
+    // the commitment a transaction has to reach to count as sent, None for the client's commitment
+    pub commitment : Option<CommitmentConfig>,
+    // how many times an expired transaction is sent again, with a new blockhash
+    pub max_retries : usize,
+    // the priority fee in micro lamports per compute unit, and the most compute units a transaction can use
+    pub compute_unit_price : Option<u64>,
+    pub compute_unit_limit : Option<u32>
+}
+
+impl Default for SendOptions {
+    fn default() -> Self {
+        SendOptions {commitment : None, max_retries : DEFAULT_SEND_RETRIES, compute_unit_price : None, compute_unit_limit : None}
+    }
+}
+
+/// Talks to the sol_verify program, and to the clusters the programs it verifies are deployed to
+pub struct VerifierClient {
+    // the cluster the sol_verify program is deployed to
+    rpc : RpcClient,
+    program_id : Pubkey,
+    clusters : ClusterConfig,
+    send_options : SendOptions,
+    // simulate transactions instead of sending them, keeping each simulation
+    dry_run : bool,
+    simulations : Mutex<Vec<Simulation>>
+}
+
+impl VerifierClient {
+
+    pub fn new(rpc : RpcClient, program_id : Pubkey, clusters : ClusterConfig) -> VerifierClient {
+        VerifierClient {rpc : rpc, program_id : program_id, clusters : clusters, send_options : SendOptions::default(), dry_run : false, simulations : Mutex::new(Vec::new())}
+    }
+
+    pub fn with_send_options(self, send_options : SendOptions) -> VerifierClient {
+        VerifierClient {send_options : send_options, ..self}
+    }
+
+    /// A client that simulates the transactions it would send instead of sending them
+    pub fn with_dry_run(self, dry_run : bool) -> VerifierClient {
+        VerifierClient {dry_run : dry_run, ..self}
+    }
+
+    /// The simulations run since the last call, oldest first
+    pub fn take_simulations(&self) -> Vec<Simulation> {
+        std::mem::take(&mut *self.simulations.lock().unwrap())
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    /// a client for the cluster programs on network are deployed to, with the same commitment as the verifier's
+    pub fn program_client(&self, network : Network) -> RpcClient {
+        RpcClient::new_with_commitment(self.clusters.url(network).to_string(), self.rpc.commitment())
+    }
+
+    /// the account holding the verification record of a program
+    pub fn metadata_address(&self, program : &Pubkey, network : Network) -> Pubkey {
+        Pubkey::find_program_address(&[&program.to_bytes(), network_to_string(network).as_bytes()], &self.program_id).0
+    }
+
+    /// the account holding the status of a user's verification
+    pub fn user_address(&self, user : &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[&user.to_bytes(), b"user_account"], &self.program_id).0
+    }
+
+    // signs and sends an instruction, after any compute budget instructions, and waits for it to reach the send
+    // commitment.  A transaction that expires first is sent again with a new blockhash, up to max_retries times
+    async fn send_instruction(&self, signer : &dyn Signer, instruction : Instruction) -> Result<String> {
+
+        let options = &self.send_options;
+        let commitment = options.commitment.unwrap_or_else(|| self.rpc.commitment());
+
+        let mut instructions = Vec::new();
+        if let Some(units) = options.compute_unit_limit {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(units));
+        }
+        if let Some(micro_lamports) = options.compute_unit_price {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(micro_lamports));
+        }
+        instructions.push(instruction);
+        let verifier_instructions : Vec<bool> = instructions.iter().map(|instruction| instruction.program_id == self.program_id).collect();
+        let failed = |signature : &Signature, err : &TransactionError| Error::TransactionFailed {
+            signature : signature.to_string(),
+            reason : describe_transaction_error(err, &verifier_instructions)
+        };
+
+        let mut signature = Signature::default();
+        for _ in 0..=options.max_retries {
+
+            let (recent_hash, last_valid_height) = self.rpc.get_latest_blockhash_with_commitment(commitment).await?;
+            let txn = Transaction::new_signed_with_payer(&instructions, Some(&signer.pubkey()), &[signer], recent_hash);
+            signature = txn.signatures[0];
+
+            // a dry run reports the signature the transaction would have had
+            if self.dry_run {
+                let simulation = self.simulate(&txn).await?;
+                self.simulations.lock().unwrap().push(simulation);
+                return Ok(signature.to_string());
+            }
+
+            // errors other than a failed preflight aren't retried, as the transaction may still land
+            let config = RpcSendTransactionConfig {preflight_commitment : Some(commitment.commitment), ..RpcSendTransactionConfig::default()};
+            if let Err(err) = self.rpc.send_transaction_with_config(&txn, config).await {
+                match err.get_transaction_error() {
+                    Some(TransactionError::BlockhashNotFound) => continue,
+                    Some(tx_err) => return Err(failed(&signature, &tx_err)),
+                    None => return Err(err.into())
+                }
+            }
+
+            loop {
+                // the height is read first, so a transaction that landed before the blockhash expired is always seen
+                let expired = self.rpc.get_block_height_with_commitment(commitment).await? > last_valid_height;
+                match self.rpc.get_signature_status_with_commitment(&signature, commitment).await? {
+                    Some(Ok(())) => return Ok(signature.to_string()),
+                    Some(Err(err)) => return Err(failed(&signature, &err)),
+                    None if expired => break,
+                    None => tokio::time::sleep(CONFIRM_POLL_INTERVAL).await
+                }
+            }
+        }
+
+        Err(Error::TransactionExpired {signature : signature.to_string(), commitment : commitment.commitment.to_string(), attempts : options.max_retries + 1})
+    }
+
+    /// Submit a program for verification, paid for by payer.  The commit is pinned to the revision the program
+    /// declares in its security.txt, if it declares one
+    pub async fn submit(&self, payer : &dyn Signer, manifest : SubmitManifest) -> Result<SubmitReport> {
+
+        let meta_data = manifest.clone().validate()?;
+
+        // the program has to be deployed on the network it is being submitted for
+        let program_client = self.program_client(meta_data.network);
+        match program_client.get_account_with_commitment(&meta_data.address, program_client.commitment()).await?.value {
+            Some(account) if account.executable => {},
+            Some(_) => return Err(Error::InvalidSubmission(format!("{} is not an executable program", meta_data.address))),
+            None => return Err(Error::InvalidSubmission(format!("program {} does not exist on {}", meta_data.address, network_to_string(meta_data.network))))
+        }
+
+        // build from the revision the program says it came from, validating again as the repo and commit may have changed
+        let program_data = fetch_program_elf(&program_client, &meta_data.address).await?;
+        let mut declared_revision = None;
+        let mut warnings = Vec::new();
+        let meta_data = match solana_security_txt::find_and_parse(&program_data) {
+            Ok(security_txt) => match pin_source(&meta_data.git_repo, &meta_data.git_commit, &meta_data.directory, &security_txt).await {
+                Ok(pinned) => {
+                    declared_revision = pinned.declared;
+                    SubmitManifest {git_repo : Some(pinned.git_repo), git_commit : Some(pinned.git_commit), directory : Some(pinned.directory), ..manifest}.validate()?
+                },
+                // without git, or a repo that can't be listed, the source is submitted as given and the verifier pins it before building
+                Err(Error::GitError(reason)) => {
+                    warnings.push(format!("the source wasn't checked against security.txt, the verifier will check it: {}", reason));
+                    meta_data
+                },
+                Err(err) => return Err(err)
+            },
+            Err(_) => meta_data
+        };
+
+        let metadata_key = self.metadata_address(&meta_data.address, meta_data.network);
+        let userdata_key = self.user_address(&payer.pubkey());
+
+        let mut report = SubmitReport {
+            program : meta_data.address.to_string(),
+            network : network_to_string(meta_data.network),
+            git_repo : meta_data.git_repo.clone(),
+            git_commit : meta_data.git_commit.clone(),
+            directory : meta_data.directory.clone(),
+            declared_revision : declared_revision,
+            warnings : warnings,
+            metadata_account : metadata_key.to_string(),
+            user_account : userdata_key.to_string(),
+            signature : String::new()
+        };
+
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &VerifyInstruction::SubmitProgram {metadata : meta_data},
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new(metadata_key, false),
+                AccountMeta::new(userdata_key, false),
+                AccountMeta::new_readonly(solana_sdk::system_program::id(), false)
+            ],
+        );
+
+        report.signature = self.send_instruction(payer, instruction).await?;
+
+        Ok(report)
+    }
+
+    /// Compare a test build with the deployed program, record the result and tell the user.  Only the verifier can sign this
+    pub async fn verify(&self, verifier : &dyn Signer, request : &VerifyRequest) -> Result<VerifyReport> {
+
+        let program_client = self.program_client(request.network);
+
+        let real_program = fetch_upgradeable_program(&program_client, &request.real_address).await?;
+        let test_program = fetch_upgradeable_program(&self.rpc, &request.test_address).await?;
+
+        let real_hash = canonical_hash(&real_program.elf);
+        let test_hash = canonical_hash(&test_program.elf);
+        let verified = real_hash == test_hash;
+        let upgradeable = real_program.upgrade_authority.is_some();
+
+        let metadata_key = self.metadata_address(&request.real_address, request.network);
+
+        let mut report = VerifyReport {
+            program : request.real_address.to_string(),
+            test_program : request.test_address.to_string(),
+            network : network_to_string(request.network),
+            user : request.user.to_string(),
+            metadata_account : metadata_key.to_string(),
+            expected_hash : to_hex(&real_hash),
+            candidate_hash : to_hex(&test_hash),
+            verified : verified,
+            upgradeable : upgradeable,
+            last_upgrade_slot : real_program.slot,
+            verified_code : None,
+            status_code : state::STATUS_FINISHED,
+            message : format!("Program {} : ", request.real_address),
+            signature : None,
+            diff : if verified { None } else { ProgramDiff::new(canonical_program_bytes(&real_program.elf), canonical_program_bytes(&test_program.elf)) }
+        };
+
+        // the commit that was built has to be the one the program declares in its security.txt
+        if let Ok(security_txt) = solana_security_txt::find_and_parse(&real_program.elf) {
+            match pin_source(&request.git_repo, &request.git_commit, &request.directory, &security_txt).await {
+                Ok(_) => {},
+                Err(err @ Error::SourceRevisionMismatch {..}) => {
+                    report.status_code = state::SOURCE_REVISION_MISMATCH;
+                    report.message += &err.to_string();
+                    self.send_status(verifier, request.user, report.status_code, VerificationStage::Comparing, None, &report.message).await?;
+                    return Ok(report);
+                },
+                Err(err) => return Err(err)
+            }
+        }
+
+        let verified_code = match (verified, upgradeable) {
+            (false, _) => state::MISMATCH,
+            (true, true) => state::MATCH_BUT_UPGRADEABLE,
+            (true, false) => state::MATCH
+        };
+
+        let current_slot = program_client.get_slot().await?;
+
+        // check the current meta data
+        let response = self.rpc.get_account_data(&metadata_key).await?;
+        let current_state = ProgramMetaData::unpack(&response[..])?;
+
+        // if the program has already had a verification run, and it has code = 3 (passed, immutable) then we only want to update
+        // the state if it still passes
+
+        if current_state.verified_code == state::MATCH && !verified {
+            report.status_code = state::PREVIOUSLY_VERIFIED_IMMUTABLE;
+            report.message += "program already verified and immutable but new verification fails.  Not updating state.";
+            self.send_status(verifier, request.user, report.status_code, VerificationStage::Finalized, Some(100), &report.message).await?;
+            return Ok(report);
+        }
+
+        // if a previous verification was code = 2 (passed, mutable), then we need to check if the last update time was after the last verification time.  If it wasn't then we only update if the verification still passes.
+
+        if current_state.verified_code == state::MATCH_BUT_UPGRADEABLE && real_program.slot < current_state.last_verified_slot && !verified {
+            report.status_code = state::PREVIOUSLY_VERIFIED_MUTABLE;
+            report.message += "program already verified and not updated since last verification but new verification fails.  Not updating state.";
+            self.send_status(verifier, request.user, report.status_code, VerificationStage::Finalized, Some(100), &report.message).await?;
+            return Ok(report);
+        }
+
+        // if the update time is after the last verification time then we will always perform the verification again.
+        // Similarly if code == 1 or 0 (failed, or this is the first time) then we will always perform it again.
+
+        let meta_data = VerifyProgramMeta {
+            verified_code : verified_code,
+            real_address : request.real_address,
+            test_address : request.test_address,
+            data_hash : test_hash,
+            verified_slot : current_slot,
+            network : request.network,
+            git_repo : request.git_repo.clone(),
+            git_commit : request.git_commit.clone(),
+            directory : request.directory.clone()
+        };
+
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &VerifyInstruction::VerifyProgram {metadata : meta_data},
+            vec![
+                AccountMeta::new_readonly(verifier.pubkey(), true),
+                AccountMeta::new(metadata_key, false),
+
+                AccountMeta::new_readonly(request.real_address, false),
+                AccountMeta::new_readonly(request.test_address, false)
+            ],
+        );
+
+        report.signature = Some(self.send_instruction(verifier, instruction).await?);
+        report.verified_code = Some(verified_code);
+
+        // finally update the user with the result
+        report.message += match verified_code {
+            state::MATCH_BUT_UPGRADEABLE => "Verification was successful, however the program is upgradable",
+            state::MATCH => "Verification was successful, and program is immutable",
+            _ => "Verification process has not produced a match"
+        };
+        self.send_status(verifier, request.user, report.status_code, VerificationStage::Finalized, Some(100), &report.message).await?;
+
+        Ok(report)
+    }
+
+    /// Set the status of a user's verification.  Only the verifier can sign this
+    pub async fn send_status(&self, verifier : &dyn Signer, user : Pubkey, status_code : u8, stage : VerificationStage, progress : Option<u8>, message : &str) -> Result<StatusUpdateReport> {
+
+        let userdata_key = self.user_address(&user);
+
+        let meta_data = StatusMeta {user_pubkey : user, status_code : status_code, stage : stage, progress : progress, log_message : message.to_string()};
+
+        let instruction = Instruction::new_with_borsh(
+            self.program_id,
+            &VerifyInstruction::UpdateStatus {metadata : meta_data},
+            vec![
+                AccountMeta::new_readonly(verifier.pubkey(), true),
+                AccountMeta::new(userdata_key, false),
+                AccountMeta::new_readonly(solana_sdk::system_program::id(), false)
+            ],
+        );
+
+        let signature = self.send_instruction(verifier, instruction).await?;
+
+        Ok(StatusUpdateReport {
+            user : user.to_string(),
+            user_account : userdata_key.to_string(),
+            status_code : status_code,
+            description : state::status_code_description(status_code).to_string(),
+            stage : stage,
+            progress : progress,
+            message : message.to_string(),
+            signature : signature
+        })
+    }
+
+    /// The verification record of a program, along with how the program is deployed now so a record made
+    /// before the program was last upgraded can be spotted
+    pub async fn program_record(&self, program : Pubkey, network : Network) -> Result<ProgramRecordReport> {
+
+        let metadata_key = self.metadata_address(&program, network);
+
+        let response = self.rpc.get_account_data(&metadata_key).await?;
+        let current_state = ProgramMetaData::unpack(&response[..])?;
+
+        let deployment = fetch_deployment(&self.program_client(network), &program).await?;
+
+        let stale = staleness(current_state.verified_code, current_state.last_verified_slot, &deployment);
+
+        Ok(ProgramRecordReport {
+            program : program.to_string(),
+            network : network_to_string(network),
+            metadata_account : metadata_key.to_string(),
+            verified_code : current_state.verified_code,
+            description : state::verified_code_description(current_state.verified_code).to_string(),
+            last_verified_slot : current_state.last_verified_slot,
+            test_address : current_state.test_address.to_string(),
+            data_hash : to_hex(&current_state.data_hash),
+            source : current_state.source(),
+            bump : current_state.bump,
+            upgradeable : deployment.upgradeable,
+            upgrade_authority : deployment.upgrade_authority.map(|authority| authority.to_string()),
+            last_deploy_slot : deployment.last_deploy_slot,
+            stale : stale
+        })
+    }
+
+    /// The current status and status history of a user's verification
+    pub async fn user_status(&self, user : Pubkey) -> Result<StatusReport> {
+
+        let userdata_key = self.user_address(&user);
+
+        let response = self.rpc.get_account_data(&userdata_key).await?;
+        let user_data = UserMetaData::unpack(&response[..])?;
+
+        Ok(StatusReport {
+            user : user.to_string(),
+            user_account : userdata_key.to_string(),
+            status_code : user_data.status_code,
+            description : state::status_code_description(user_data.status_code).to_string(),
+            stage : user_data.stage,
+            progress : user_data.latest().and_then(|latest| latest.progress),
+            message : user_data.latest().map(|latest| latest.log_message.clone()),
+            history : user_data.history
+        })
+    }
+
+    /// The security.txt of a program deployed to the verifier's cluster
+    pub async fn security_report(&self, program : Pubkey) -> Result<SecurityReport> {
+
+        fetch_security_report(&self.rpc, &program).await
+    }
+}
@@ -0,0 +1,328 @@
+//! Decodes the sol_verify instructions in a transaction, labelling each account with the role the program gives it,
+//! and explains the program's custom errors in the transaction's error and logs
+
+use crate::state::{self, Error, Result, VerifyInstruction, network_to_string};
+use crate::client::VerifierClient;
+use crate::hash::to_hex;
+
+use serde::Serialize;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_program::{instruction::InstructionError, pubkey::Pubkey};
+use solana_sdk::{bs58, signature::Signature, system_program, transaction::TransactionError};
+use solana_transaction_status::{UiInstruction, UiTransactionEncoding};
+use std::str::FromStr;
+
+/// An account passed to an instruction
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountRef {
+    pub address : Pubkey,
+    pub signer : bool,
+    pub writable : bool
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LabelledAccount {
+    pub address : String,
+    // what the program uses the account as, None for accounts it doesn't read
+    pub role : Option<String>,
+    pub signer : bool,
+    pub writable : bool,
+    // the address the instruction's data says this account should have, when it has another
+    pub expected : Option<String>
+}
+
+/// A sol_verify instruction, with its data decoded
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "instruction", rename_all = "snake_case")]
+pub enum DecodedCall {
+    SubmitProgram {
+        program : String,
+        network : String,
+        git_repo : String,
+        git_commit : String,
+        directory : String,
+        docker_version : String,
+        rust_version : String,
+        solana_version : String,
+        anchor_version : String
+    },
+    VerifyProgram {
+        program : String,
+        test_program : String,
+        network : String,
+        verified_code : u8,
+        description : String,
+        data_hash : String,
+        verified_slot : u64,
+        git_repo : String,
+        git_commit : String,
+        directory : String
+    },
+    UpdateStatus {
+        user : String,
+        status_code : u8,
+        description : String,
+        stage : String,
+        progress : Option<u8>,
+        message : String
+    },
+    // data the program would reject as an invalid instruction, in hex
+    Undecodable {
+        data : String
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DecodedInstruction {
+    // position of the instruction in the transaction, or of the instruction that invoked it
+    pub index : usize,
+    // position among the instructions invoked by index, None for instructions in the transaction itself
+    pub inner_index : Option<usize>,
+    #[serde(flatten)]
+    pub call : DecodedCall,
+    pub accounts : Vec<LabelledAccount>
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DecodedTransaction {
+    pub signature : String,
+    pub slot : u64,
+    pub block_time : Option<i64>,
+    pub fee : Option<u64>,
+    // None if the transaction succeeded
+    pub error : Option<String>,
+    pub instructions : Vec<DecodedInstruction>,
+    pub logs : Vec<String>
+}
+
+impl DecodedTransaction {
+    pub fn print(&self) {
+        println!("signature: {}", self.signature);
+        println!("slot: {}", self.slot);
+        if let Some(block_time) = self.block_time {
+            println!("block time: {}", block_time);
+        }
+        if let Some(fee) = self.fee {
+            println!("fee: {} lamports", fee);
+        }
+        match &self.error {
+            Some(error) => println!("failed: {}", error),
+            None => println!("succeeded")
+        }
+        if self.instructions.is_empty() {
+            println!("no sol_verify instructions");
+        }
+        for instruction in self.instructions.iter() {
+            print_instruction(instruction);
+        }
+        if !self.logs.is_empty() {
+            println!("logs:");
+            for log in self.logs.iter() {
+                println!("  {}", log);
+            }
+        }
+    }
+}
+
+pub fn print_instruction(instruction : &DecodedInstruction) {
+    // the fields print more readably as json than as a debug string
+    let fields = match serde_json::to_value(&instruction.call) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new()
+    };
+    let name = fields.get("instruction").and_then(|name| name.as_str()).unwrap_or_default();
+
+    match instruction.inner_index {
+        Some(inner_index) => println!("instruction {}.{} {} (invoked by instruction {}):", instruction.index, inner_index, name, instruction.index),
+        None => println!("instruction {} {}:", instruction.index, name)
+    }
+    for (field, value) in fields.iter().filter(|(field, _)| *field != "instruction") {
+        println!("  {}: {}", field, value);
+    }
+    println!("  accounts:");
+    for account in instruction.accounts.iter() {
+        let flags = match (account.signer, account.writable) {
+            (true, true) => " (signer, writable)",
+            (true, false) => " (signer)",
+            (false, true) => " (writable)",
+            (false, false) => ""
+        };
+        println!("    {}: {}{}", account.role.as_deref().unwrap_or("unused"), account.address, flags);
+        if let Some(expected) = &account.expected {
+            println!("      expected {}", expected);
+        }
+    }
+}
+
+/// A custom error from the program as its name and description, or None for any other code
+pub fn describe_program_error(code : u32) -> Option<String> {
+    state::program_error(code).map(|(name, description)| format!("{} ({})", name, description))
+}
+
+/// A transaction error, with the program's custom errors named.  verifier_instructions is whether the instruction at
+/// each index was to the verifier program, as other programs' custom errors mean something else
+pub fn describe_transaction_error(error : &TransactionError, verifier_instructions : &[bool]) -> String {
+    if let TransactionError::InstructionError(index, InstructionError::Custom(code)) = error {
+        if verifier_instructions.get(*index as usize) == Some(&true) {
+            if let Some(described) = describe_program_error(*code) {
+                return format!("instruction {} failed: {}", index, described);
+            }
+        }
+    }
+    error.to_string()
+}
+
+/// Log lines with the verifier program's custom errors named
+pub fn describe_logs(logs : &[String], program_id : &Pubkey) -> Vec<String> {
+    let failed = format!("Program {} failed: custom program error: 0x", program_id);
+    logs.iter().map(|log| {
+        let described = log.strip_prefix(&failed)
+            .and_then(|code| u32::from_str_radix(code, 16).ok())
+            .and_then(describe_program_error);
+        match described {
+            Some(described) => format!("{} {}", log, described),
+            None => log.clone()
+        }
+    }).collect()
+}
+
+impl VerifierClient {
+
+    // the roles of an instruction's accounts, and the address each should have if the data implies one
+    fn account_roles(&self, instruction : &VerifyInstruction, accounts : &[AccountRef]) -> Vec<(&'static str, Option<Pubkey>)> {
+        match instruction {
+            VerifyInstruction::SubmitProgram {metadata} => vec![
+                ("owner", None),
+                ("metadata PDA", Some(self.metadata_address(&metadata.address, metadata.network))),
+                ("user PDA", accounts.first().map(|owner| self.user_address(&owner.address))),
+                ("system program", Some(system_program::id()))
+            ],
+            VerifyInstruction::VerifyProgram {metadata} => vec![
+                ("verifier", None),
+                ("metadata PDA", Some(self.metadata_address(&metadata.real_address, metadata.network))),
+                ("program", Some(metadata.real_address)),
+                ("test program", Some(metadata.test_address))
+            ],
+            VerifyInstruction::UpdateStatus {metadata} => vec![
+                ("verifier", None),
+                ("user PDA", Some(self.user_address(&metadata.user_pubkey))),
+                ("system program", Some(system_program::id()))
+            ]
+        }
+    }
+
+    /// Decodes one instruction to the verifier program
+    pub fn decode_instruction(&self, index : usize, inner_index : Option<usize>, data : &[u8], accounts : &[AccountRef]) -> DecodedInstruction {
+
+        let instruction = VerifyInstruction::unpack(data).ok();
+        let roles = instruction.as_ref().map(|instruction| self.account_roles(instruction, accounts)).unwrap_or_default();
+
+        let labelled = accounts.iter().enumerate().map(|(position, account)| {
+            let (role, expected) = match roles.get(position) {
+                Some((role, expected)) => (Some(role.to_string()), *expected),
+                None => (None, None)
+            };
+            LabelledAccount {
+                address : account.address.to_string(),
+                role : role,
+                signer : account.signer,
+                writable : account.writable,
+                expected : expected.filter(|expected| *expected != account.address).map(|expected| expected.to_string())
+            }
+        }).collect();
+
+        let call = match instruction {
+            Some(VerifyInstruction::SubmitProgram {metadata}) => DecodedCall::SubmitProgram {
+                program : metadata.address.to_string(),
+                network : network_to_string(metadata.network),
+                git_repo : metadata.git_repo,
+                git_commit : metadata.git_commit,
+                directory : metadata.directory,
+                docker_version : metadata.docker_version,
+                rust_version : metadata.rust_version,
+                solana_version : metadata.solana_version,
+                anchor_version : metadata.anchor_version
+            },
+            Some(VerifyInstruction::VerifyProgram {metadata}) => DecodedCall::VerifyProgram {
+                program : metadata.real_address.to_string(),
+                test_program : metadata.test_address.to_string(),
+                network : network_to_string(metadata.network),
+                verified_code : metadata.verified_code,
+                description : state::verified_code_description(metadata.verified_code).to_string(),
+                data_hash : to_hex(&metadata.data_hash),
+                verified_slot : metadata.verified_slot,
+                git_repo : metadata.git_repo,
+                git_commit : metadata.git_commit,
+                directory : metadata.directory
+            },
+            Some(VerifyInstruction::UpdateStatus {metadata}) => DecodedCall::UpdateStatus {
+                user : metadata.user_pubkey.to_string(),
+                status_code : metadata.status_code,
+                description : state::status_code_description(metadata.status_code).to_string(),
+                stage : metadata.stage.name().to_string(),
+                progress : metadata.progress,
+                message : metadata.log_message
+            },
+            None => DecodedCall::Undecodable {data : to_hex(data)}
+        };
+
+        DecodedInstruction {index : index, inner_index : inner_index, call : call, accounts : labelled}
+    }
+
+    /// Fetches a transaction and decodes every sol_verify instruction in it, including those invoked by other programs
+    pub async fn decode_transaction(&self, signature : &Signature) -> Result<DecodedTransaction> {
+
+        let config = RpcTransactionConfig {encoding : Some(UiTransactionEncoding::Base64), commitment : None, max_supported_transaction_version : Some(0)};
+        let confirmed = self.rpc().get_transaction_with_config(signature, config).await?;
+        let transaction = confirmed.transaction.transaction.decode()
+            .ok_or_else(|| Error::InvalidConfig(format!("transaction {} couldn't be decoded", signature)))?;
+        let meta = confirmed.transaction.meta;
+        let message = &transaction.message;
+
+        // accounts loaded from lookup tables follow the static ones, writable first
+        let mut keys : Vec<AccountRef> = message.static_account_keys().iter().enumerate()
+            .map(|(index, key)| AccountRef {address : *key, signer : message.is_signer(index), writable : message.is_maybe_writable(index)})
+            .collect();
+        if let Some(loaded) = meta.as_ref().and_then(|meta| meta.loaded_addresses.as_ref()) {
+            for (addresses, writable) in [(&loaded.writable, true), (&loaded.readonly, false)] {
+                for address in addresses.iter() {
+                    let address = Pubkey::from_str(address).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+                    keys.push(AccountRef {address : address, signer : false, writable : writable});
+                }
+            }
+        }
+
+        let resolve = |indexes : &[u8]| -> Vec<AccountRef> { indexes.iter().filter_map(|index| keys.get(*index as usize).cloned()).collect() };
+        let is_verifier = |program_id_index : u8| keys.get(program_id_index as usize).map(|key| key.address) == Some(self.program_id());
+
+        let mut instructions = Vec::new();
+        for (index, instruction) in message.instructions().iter().enumerate() {
+            if is_verifier(instruction.program_id_index) {
+                instructions.push(self.decode_instruction(index, None, &instruction.data, &resolve(&instruction.accounts)));
+            }
+
+            let inner = meta.as_ref().and_then(|meta| meta.inner_instructions.as_ref())
+                .and_then(|inner| inner.iter().find(|inner| inner.index as usize == index));
+            for (inner_index, inner_instruction) in inner.map(|inner| inner.instructions.iter().enumerate()).into_iter().flatten() {
+                if let UiInstruction::Compiled(compiled) = inner_instruction {
+                    if is_verifier(compiled.program_id_index) {
+                        let data = bs58::decode(&compiled.data).into_vec().map_err(|err| Error::InvalidConfig(err.to_string()))?;
+                        instructions.push(self.decode_instruction(index, Some(inner_index), &data, &resolve(&compiled.accounts)));
+                    }
+                }
+            }
+        }
+
+        let verifier_instructions : Vec<bool> = message.instructions().iter().map(|instruction| is_verifier(instruction.program_id_index)).collect();
+
+        Ok(DecodedTransaction {
+            signature : signature.to_string(),
+            slot : confirmed.slot,
+            block_time : confirmed.block_time,
+            fee : meta.as_ref().map(|meta| meta.fee),
+            error : meta.as_ref().and_then(|meta| meta.err.as_ref()).map(|err| describe_transaction_error(err, &verifier_instructions)),
+            instructions : instructions,
+            logs : describe_logs(&meta.as_ref().and_then(|meta| meta.log_messages.clone()).unwrap_or_default(), &self.program_id())
+        })
+    }
+}
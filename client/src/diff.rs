@@ -0,0 +1,167 @@
+use crate::elf::{Elf, Symbol};
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// symbol changes beyond this are counted rather than listed
+const MAX_SYMBOLS_SHOWN : usize = 20;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SectionDiff {
+    pub name : String,
+    pub expected_size : Option<usize>,
+    pub candidate_size : Option<usize>,
+    // offset from the start of the section of the first byte that differs, None if the section is the same in both
+    pub first_difference : Option<usize>
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SymbolChange {
+    Added,
+    Removed,
+    Changed {expected_size : u64, candidate_size : u64, expected_value : u64, candidate_value : u64}
+}
+
+/// The differences between a program as expected (usually the one on chain) and a candidate build of it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProgramDiff {
+    pub sections : Vec<SectionDiff>,
+    pub symbols : Vec<(String, SymbolChange)>
+}
+
+fn first_difference(expected : &[u8], candidate : &[u8]) -> Option<usize> {
+    match expected.iter().zip(candidate).position(|(a, b)| a != b) {
+        Some(offset) => Some(offset),
+        None if expected.len() != candidate.len() => Some(expected.len().min(candidate.len())),
+        None => None
+    }
+}
+
+fn symbol_map(symbols : Vec<Symbol>) -> BTreeMap<String, Symbol> {
+    symbols.into_iter().filter(|s| !s.name.is_empty()).map(|s| (s.name.clone(), s)).collect()
+}
+
+impl ProgramDiff {
+
+    /// Returns None if either program isn't an ELF
+    pub fn new(expected : &[u8], candidate : &[u8]) -> Option<ProgramDiff> {
+
+        let expected_elf = Elf::parse(expected)?;
+        let candidate_elf = Elf::parse(candidate)?;
+
+        // sections are matched by name, in the order they appear in the expected program followed by any only in the candidate
+        let mut sections = Vec::new();
+        for section in expected_elf.sections.iter().filter(|s| !s.name.is_empty()) {
+            let other = candidate_elf.section(&section.name);
+            sections.push(SectionDiff {
+                name : section.name.clone(),
+                expected_size : Some(section.size),
+                candidate_size : other.map(|s| s.size),
+                first_difference : match other {
+                    // sections without data, like .bss, can still differ in size
+                    Some(other) => first_difference(section.data(expected), other.data(candidate))
+                        .or_else(|| (section.size != other.size).then_some(0)),
+                    None => Some(0)
+                }
+            });
+        }
+
+        for section in candidate_elf.sections.iter().filter(|s| !s.name.is_empty()) {
+            if expected_elf.section(&section.name).is_none() {
+                sections.push(SectionDiff {
+                    name : section.name.clone(),
+                    expected_size : None,
+                    candidate_size : Some(section.size),
+                    first_difference : Some(0)
+                });
+            }
+        }
+
+        let expected_symbols = symbol_map(expected_elf.dynamic_symbols(expected));
+        let candidate_symbols = symbol_map(candidate_elf.dynamic_symbols(candidate));
+
+        let mut symbols = Vec::new();
+        for (name, symbol) in &expected_symbols {
+            match candidate_symbols.get(name) {
+                None => symbols.push((name.clone(), SymbolChange::Removed)),
+                Some(other) if other.size != symbol.size || other.value != symbol.value => symbols.push((name.clone(), SymbolChange::Changed {
+                    expected_size : symbol.size,
+                    candidate_size : other.size,
+                    expected_value : symbol.value,
+                    candidate_value : other.value
+                })),
+                Some(_) => {}
+            }
+        }
+
+        for name in candidate_symbols.keys() {
+            if !expected_symbols.contains_key(name) {
+                symbols.push((name.clone(), SymbolChange::Added));
+            }
+        }
+
+        Some(ProgramDiff {sections : sections, symbols : symbols})
+    }
+
+    pub fn differing_sections(&self) -> impl Iterator<Item = &SectionDiff> {
+        self.sections.iter().filter(|s| s.first_difference.is_some())
+    }
+
+    /// a one line reading of the differences, to help tell a toolchain mismatch from a change in the code
+    pub fn summary(&self) -> &'static str {
+
+        let symbols_added_or_removed = self.symbols.iter().any(|(_, change)| matches!(change, SymbolChange::Added | SymbolChange::Removed));
+
+        if self.differing_sections().next().is_none() {
+            return "all sections match, the programs differ only in their headers or layout";
+        }
+
+        if symbols_added_or_removed {
+            return "the programs export different symbols, so the source code differs";
+        }
+
+        if self.differing_sections().all(|s| s.name == ".comment") {
+            return "only the compiler identification differs, the code is the same";
+        }
+
+        if self.symbols.is_empty() && self.differing_sections().all(|s| s.expected_size == s.candidate_size) {
+            return "sizes and symbols match but contents differ, which is usually a small code change or different build flags";
+        }
+
+        "the same symbols are laid out differently, which is typical of building with a different toolchain version"
+    }
+
+    pub fn print(&self) {
+
+        println!("sections:");
+        println!("  {:<24} {:>12} {:>12}  first difference", "name", "expected", "candidate");
+        for section in &self.sections {
+            let size = |size : Option<usize>| size.map_or("-".to_string(), |s| s.to_string());
+            let difference = match (section.first_difference, section.expected_size, section.candidate_size) {
+                (None, _, _) => "same".to_string(),
+                (Some(_), None, _) => "only in candidate".to_string(),
+                (Some(_), _, None) => "only in expected".to_string(),
+                (Some(offset), _, _) => format!("{:#x}", offset)
+            };
+            println!("  {:<24} {:>12} {:>12}  {}", section.name, size(section.expected_size), size(section.candidate_size), difference);
+        }
+
+        if !self.symbols.is_empty() {
+            println!("dynamic symbols:");
+            for (name, change) in self.symbols.iter().take(MAX_SYMBOLS_SHOWN) {
+                match change {
+                    SymbolChange::Added => println!("  + {}", name),
+                    SymbolChange::Removed => println!("  - {}", name),
+                    SymbolChange::Changed {expected_size, candidate_size, expected_value, candidate_value} =>
+                        println!("  ~ {} size {} -> {}, address {:#x} -> {:#x}", name, expected_size, candidate_size, expected_value, candidate_value)
+                }
+            }
+            if self.symbols.len() > MAX_SYMBOLS_SHOWN {
+                println!("  ... and {} more", self.symbols.len() - MAX_SYMBOLS_SHOWN);
+            }
+        }
+
+        println!("summary: {}", self.summary());
+    }
+}
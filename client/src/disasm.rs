@@ -0,0 +1,488 @@
+use crate::state::{Error, Result};
+
+use serde::Serialize;
+use solana_rbpf::{
+    disassembler::disassemble_instruction,
+    ebpf,
+    elf::Executable,
+    static_analysis::Analysis,
+    user_error::UserError,
+    vm::{Config, SyscallRegistry, TestInstructionMeter}
+};
+use std::collections::{BTreeMap, HashSet};
+
+// lines either side of a change that are shown in the text output
+const CONTEXT_LINES : usize = 3;
+
+// past this many cells the alignment falls back to comparing instructions in order
+const MAX_ALIGNMENT_CELLS : usize = 16_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub text : String,
+    pub opc : u8,
+    pub dst : u8,
+    pub src : u8,
+    pub off : i16,
+    pub imm : i64,
+    // whether imm is an address in the program, which moves whenever anything before its target is added or removed
+    pub address_operand : bool
+}
+
+impl Instruction {
+    // instructions are aligned on everything but those addresses, so that a moved constant still lines up
+    fn alignment_key(&self) -> (u8, u8, u8, i16, i64, bool) {
+        let imm = if self.address_operand { 0 } else { self.imm };
+        (self.opc, self.dst, self.src, self.off, imm, self.address_operand)
+    }
+
+    fn exactly_equal(&self, other : &Instruction) -> bool {
+        (self.opc, self.dst, self.src, self.off, self.imm) == (other.opc, other.dst, other.src, other.off, other.imm)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name : String,
+    pub instructions : Vec<Instruction>
+}
+
+impl Function {
+    // without a symbol table the loader names functions after their position, which can't be matched between builds
+    fn has_symbol_name(&self) -> bool {
+        !self.name.starts_with("function_")
+    }
+}
+
+/// Disassemble the text section of a program, split up into its functions
+pub fn disassemble(elf : &[u8]) -> Result<Vec<Function>> {
+
+    let config = Config {
+        enable_symbol_and_section_labels : true,
+        disable_unresolved_symbols_at_runtime : false,
+        ..Config::default()
+    };
+
+    let executable = Executable::<UserError, TestInstructionMeter>::load(config, elf, SyscallRegistry::default())
+        .map_err(|err| Error::DisassemblyError(err.to_string()))?;
+    let analysis = Analysis::from_executable(&executable);
+
+    // the text and read only sections are loaded together from here
+    let program_addresses = ebpf::MM_PROGRAM_START..ebpf::MM_PROGRAM_START + executable.get_ro_section().len() as u64;
+
+    let mut functions : Vec<Function> = Vec::new();
+    for insn in &analysis.instructions {
+        if functions.is_empty() || analysis.functions.contains_key(&insn.ptr) {
+            let name = analysis.cfg_nodes.get(&insn.ptr).map_or(format!("function_{}", insn.ptr), |node| node.label.clone());
+            functions.push(Function {name : name, instructions : Vec::new()});
+        }
+
+        // lddw loads an address when it points into the program, and is otherwise a constant.  Calls to the program's own
+        // functions carry an address, while a syscall's imm is the hash of its name, so a different one is a different call
+        let address_operand = match insn.opc {
+            ebpf::LD_DW_IMM => program_addresses.contains(&(insn.imm as u64)),
+            ebpf::CALL_IMM => executable.lookup_bpf_function(insn.imm as u32).is_some(),
+            _ => false
+        };
+
+        functions.last_mut().unwrap().instructions.push(Instruction {
+            text : disassemble_instruction(insn, &analysis),
+            opc : insn.opc,
+            dst : insn.dst,
+            src : insn.src,
+            off : insn.off,
+            imm : insn.imm,
+            address_operand : address_operand
+        });
+    }
+
+    Ok(functions)
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DiffLine {
+    Same {text : String},
+    // the same instruction with a different address, i.e. a constant, string or function that moved
+    Moved {expected : String, candidate : String},
+    // an instruction replaced by a different one
+    Changed {expected : String, candidate : String},
+    Removed {text : String},
+    Added {text : String}
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FunctionChange {
+    // only addresses of constants, strings or called functions differ
+    PlacementOnly,
+    // instructions were added, removed or changed
+    Logic
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FunctionDiff {
+    pub expected_name : String,
+    pub candidate_name : String,
+    pub change : FunctionChange,
+    pub lines : Vec<DiffLine>
+}
+
+/// The functions that differ between two programs.  Functions are paired by name where both programs have
+/// symbols, and in order of appearance otherwise
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DisassemblyDiff {
+    pub identical_functions : usize,
+    pub functions : Vec<FunctionDiff>,
+    pub only_expected : Vec<String>,
+    pub only_candidate : Vec<String>
+}
+
+// longest common subsequence alignment of the two instruction lists
+fn align(expected : &[Instruction], candidate : &[Instruction]) -> Vec<DiffLine> {
+
+    let n = expected.len();
+    let m = candidate.len();
+
+    let pair = |a : &Instruction, b : &Instruction| {
+        if a.exactly_equal(b) {
+            DiffLine::Same {text : a.text.clone()}
+        }
+        else {
+            DiffLine::Moved {expected : a.text.clone(), candidate : b.text.clone()}
+        }
+    };
+
+    if (n + 1).saturating_mul(m + 1) > MAX_ALIGNMENT_CELLS {
+        let mut lines : Vec<DiffLine> = Vec::with_capacity(n.max(m));
+        for (a, b) in expected.iter().zip(candidate) {
+            if a.alignment_key() == b.alignment_key() {
+                lines.push(pair(a, b));
+            }
+            else {
+                lines.push(DiffLine::Removed {text : a.text.clone()});
+                lines.push(DiffLine::Added {text : b.text.clone()});
+            }
+        }
+        lines.extend(expected.iter().skip(m).map(|a| DiffLine::Removed {text : a.text.clone()}));
+        lines.extend(candidate.iter().skip(n).map(|b| DiffLine::Added {text : b.text.clone()}));
+        return pair_replacements(lines);
+    }
+
+    // lengths[i][j] is the length of the common subsequence of expected[i..] and candidate[j..]
+    let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+    let index = |i : usize, j : usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[index(i, j)] = if expected[i].alignment_key() == candidate[j].alignment_key() {
+                lengths[index(i + 1, j + 1)] + 1
+            }
+            else {
+                lengths[index(i + 1, j)].max(lengths[index(i, j + 1)])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if expected[i].alignment_key() == candidate[j].alignment_key() {
+            lines.push(pair(&expected[i], &candidate[j]));
+            i += 1;
+            j += 1;
+        }
+        else if lengths[index(i + 1, j)] >= lengths[index(i, j + 1)] {
+            lines.push(DiffLine::Removed {text : expected[i].text.clone()});
+            i += 1;
+        }
+        else {
+            lines.push(DiffLine::Added {text : candidate[j].text.clone()});
+            j += 1;
+        }
+    }
+    lines.extend(expected[i..].iter().map(|a| DiffLine::Removed {text : a.text.clone()}));
+    lines.extend(candidate[j..].iter().map(|b| DiffLine::Added {text : b.text.clone()}));
+
+    pair_replacements(lines)
+}
+
+// a run of removed instructions followed by a run of added ones reads better as instructions that were changed
+fn pair_replacements(lines : Vec<DiffLine>) -> Vec<DiffLine> {
+
+    let mut paired = Vec::with_capacity(lines.len());
+    let mut removed : Vec<String> = Vec::new();
+    let mut added : Vec<String> = Vec::new();
+
+    let flush = |paired : &mut Vec<DiffLine>, removed : &mut Vec<String>, added : &mut Vec<String>| {
+        let changed = removed.len().min(added.len());
+        for (expected, candidate) in removed.drain(..changed).zip(added.drain(..changed)) {
+            paired.push(DiffLine::Changed {expected : expected, candidate : candidate});
+        }
+        paired.extend(removed.drain(..).map(|text| DiffLine::Removed {text : text}));
+        paired.extend(added.drain(..).map(|text| DiffLine::Added {text : text}));
+    };
+
+    for line in lines {
+        match line {
+            DiffLine::Removed {text} if added.is_empty() => removed.push(text),
+            DiffLine::Added {text} => added.push(text),
+            line => {
+                flush(&mut paired, &mut removed, &mut added);
+                match line {
+                    DiffLine::Removed {text} => removed.push(text),
+                    line => paired.push(line)
+                }
+            }
+        }
+    }
+    flush(&mut paired, &mut removed, &mut added);
+
+    paired
+}
+
+impl DisassemblyDiff {
+
+    pub fn new(expected : &[Function], candidate : &[Function]) -> DisassemblyDiff {
+
+        let expected_names : HashSet<&str> = expected.iter().filter(|f| f.has_symbol_name()).map(|f| f.name.as_str()).collect();
+        let candidate_by_name : BTreeMap<&str, &Function> = candidate.iter().filter(|f| f.has_symbol_name()).map(|f| (f.name.as_str(), f)).collect();
+
+        // named functions are paired by name, everything else in order
+        let mut pairs : Vec<(&Function, &Function)> = Vec::new();
+        let mut expected_unnamed = Vec::new();
+        let mut only_expected = Vec::new();
+        for function in expected {
+            match candidate_by_name.get(function.name.as_str()) {
+                Some(other) => pairs.push((function, other)),
+                None if function.has_symbol_name() => only_expected.push(function.name.clone()),
+                None => expected_unnamed.push(function)
+            }
+        }
+
+        let mut candidate_unnamed = Vec::new();
+        let mut only_candidate = Vec::new();
+        for function in candidate {
+            if !function.has_symbol_name() {
+                candidate_unnamed.push(function);
+            }
+            else if !expected_names.contains(function.name.as_str()) {
+                only_candidate.push(function.name.clone());
+            }
+        }
+
+        pairs.extend(expected_unnamed.iter().copied().zip(candidate_unnamed.iter().copied()));
+        only_expected.extend(expected_unnamed.iter().skip(candidate_unnamed.len()).map(|f| f.name.clone()));
+        only_candidate.extend(candidate_unnamed.iter().skip(expected_unnamed.len()).map(|f| f.name.clone()));
+
+        let mut identical_functions = 0;
+        let mut functions = Vec::new();
+        for (a, b) in pairs {
+            let lines = align(&a.instructions, &b.instructions);
+
+            if lines.iter().all(|line| matches!(line, DiffLine::Same {..})) {
+                identical_functions += 1;
+                continue;
+            }
+
+            let change = if lines.iter().any(|line| matches!(line, DiffLine::Changed {..} | DiffLine::Removed {..} | DiffLine::Added {..})) {
+                FunctionChange::Logic
+            }
+            else {
+                FunctionChange::PlacementOnly
+            };
+
+            functions.push(FunctionDiff {
+                expected_name : a.name.clone(),
+                candidate_name : b.name.clone(),
+                change : change,
+                lines : lines
+            });
+        }
+
+        DisassemblyDiff {
+            identical_functions : identical_functions,
+            functions : functions,
+            only_expected : only_expected,
+            only_candidate : only_candidate
+        }
+    }
+
+    pub fn has_logic_changes(&self) -> bool {
+        !self.only_expected.is_empty() || !self.only_candidate.is_empty() || self.functions.iter().any(|f| f.change == FunctionChange::Logic)
+    }
+
+    /// side by side listing of each differing function, with unchanged runs cut down to a few lines of context
+    pub fn print(&self) {
+
+        for function in &self.functions {
+            let title = if function.expected_name == function.candidate_name {
+                function.expected_name.clone()
+            }
+            else {
+                format!("{} / {}", function.expected_name, function.candidate_name)
+            };
+            let change = match function.change {
+                FunctionChange::PlacementOnly => "addresses only",
+                FunctionChange::Logic => "instructions differ"
+            };
+            println!("{} ({}):", title, change);
+
+            let changed : Vec<bool> = function.lines.iter().map(|line| !matches!(line, DiffLine::Same {..})).collect();
+            let mut skipped = false;
+            for (i, line) in function.lines.iter().enumerate() {
+                let start = i.saturating_sub(CONTEXT_LINES);
+                let end = (i + CONTEXT_LINES + 1).min(changed.len());
+                if !changed[start..end].iter().any(|c| *c) {
+                    if !skipped {
+                        println!("    ...");
+                        skipped = true;
+                    }
+                    continue;
+                }
+                skipped = false;
+
+                match line {
+                    DiffLine::Same {text} => println!("    {:<40}   {}", text, text),
+                    DiffLine::Moved {expected, candidate} => println!("  ~ {:<40} | {}", expected, candidate),
+                    DiffLine::Changed {expected, candidate} => println!("  ! {:<40} | {}", expected, candidate),
+                    DiffLine::Removed {text} => println!("  - {:<40} <", text),
+                    DiffLine::Added {text} => println!("  + {:<40} > {}", "", text)
+                }
+            }
+        }
+
+        for name in &self.only_expected {
+            println!("only in expected: {}", name);
+        }
+        for name in &self.only_candidate {
+            println!("only in candidate: {}", name);
+        }
+
+        println!("{} functions identical, {} differ", self.identical_functions, self.functions.len());
+        if self.functions.is_empty() && self.only_expected.is_empty() && self.only_candidate.is_empty() {
+            println!("summary: the code is the same, any difference is in data or metadata");
+        }
+        else if self.has_logic_changes() {
+            println!("summary: instructions differ, the programs do not run the same code");
+        }
+        else {
+            println!("summary: only the placement of constants, strings or functions differs");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(text : &str, opc : u8, imm : i64, address_operand : bool) -> Instruction {
+        Instruction {text : text.to_string(), opc : opc, dst : 0, src : 0, off : 0, imm : imm, address_operand : address_operand}
+    }
+
+    fn mov(imm : i64) -> Instruction {
+        instruction(&format!("mov64 r0, {}", imm), ebpf::MOV64_IMM, imm, false)
+    }
+
+    // an address in the program
+    fn lddw(imm : i64) -> Instruction {
+        instruction(&format!("lddw r0, {:#x}", imm), ebpf::LD_DW_IMM, imm, true)
+    }
+
+    fn lddw_constant(imm : i64) -> Instruction {
+        instruction(&format!("lddw r0, {:#x}", imm), ebpf::LD_DW_IMM, imm, false)
+    }
+
+    fn call(imm : i64) -> Instruction {
+        instruction(&format!("call {:#x}", imm), ebpf::CALL_IMM, imm, true)
+    }
+
+    fn syscall(imm : i64) -> Instruction {
+        instruction(&format!("syscall {:#x}", imm), ebpf::CALL_IMM, imm, false)
+    }
+
+    fn function(name : &str, instructions : Vec<Instruction>) -> Function {
+        Function {name : name.to_string(), instructions : instructions}
+    }
+
+    fn same(instruction : &Instruction) -> DiffLine {
+        DiffLine::Same {text : instruction.text.clone()}
+    }
+
+    #[test]
+    fn identical_instructions_are_the_same() {
+        let instructions = vec![mov(1), lddw(0x100), mov(2)];
+        assert_eq!(align(&instructions, &instructions), instructions.iter().map(same).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn addresses_that_moved_still_line_up() {
+        let lines = align(&[mov(1), lddw(0x100), mov(2)], &[mov(1), lddw(0x200), mov(2)]);
+        assert_eq!(lines, vec![
+            same(&mov(1)),
+            DiffLine::Moved {expected : lddw(0x100).text, candidate : lddw(0x200).text},
+            same(&mov(2))
+        ]);
+    }
+
+    #[test]
+    fn calls_to_moved_functions_still_line_up() {
+        let lines = align(&[mov(1), call(0x10)], &[mov(1), call(0x20)]);
+        assert_eq!(lines, vec![same(&mov(1)), DiffLine::Moved {expected : call(0x10).text, candidate : call(0x20).text}]);
+    }
+
+    #[test]
+    fn changed_constants_are_logic_changes() {
+        let lines = align(&[mov(1), lddw_constant(0x1_0000_0000)], &[mov(1), lddw_constant(0x2_0000_0000)]);
+        assert_eq!(lines, vec![same(&mov(1)), DiffLine::Changed {expected : lddw_constant(0x1_0000_0000).text, candidate : lddw_constant(0x2_0000_0000).text}]);
+
+        let diff = DisassemblyDiff::new(&[function("entrypoint", vec![lddw_constant(1)])], &[function("entrypoint", vec![lddw_constant(2)])]);
+        assert!(diff.has_logic_changes());
+    }
+
+    #[test]
+    fn different_syscalls_are_logic_changes() {
+        // sol_log_ and sol_log_64_
+        let lines = align(&[syscall(0x207559bd)], &[syscall(0x5c2a3178)]);
+        assert_eq!(lines, vec![DiffLine::Changed {expected : syscall(0x207559bd).text, candidate : syscall(0x5c2a3178).text}]);
+
+        let diff = DisassemblyDiff::new(&[function("entrypoint", vec![syscall(0x207559bd)])], &[function("entrypoint", vec![syscall(0x5c2a3178)])]);
+        assert_eq!(diff.functions[0].change, FunctionChange::Logic);
+        assert!(diff.has_logic_changes());
+    }
+
+    #[test]
+    fn moved_addresses_are_placement_only() {
+        let diff = DisassemblyDiff::new(&[function("entrypoint", vec![lddw(0x100), call(0x10)])], &[function("entrypoint", vec![lddw(0x200), call(0x20)])]);
+        assert_eq!(diff.functions[0].change, FunctionChange::PlacementOnly);
+        assert!(!diff.has_logic_changes());
+    }
+
+    #[test]
+    fn insertions_and_removals_are_aligned() {
+        let lines = align(&[mov(1), mov(2), mov(3)], &[mov(1), mov(9), mov(2), mov(3)]);
+        assert_eq!(lines, vec![same(&mov(1)), DiffLine::Added {text : mov(9).text}, same(&mov(2)), same(&mov(3))]);
+
+        let lines = align(&[mov(1), mov(2), mov(3)], &[mov(1), mov(2)]);
+        assert_eq!(lines, vec![same(&mov(1)), same(&mov(2)), DiffLine::Removed {text : mov(3).text}]);
+
+        assert_eq!(align(&[], &[mov(1)]), vec![DiffLine::Added {text : mov(1).text}]);
+    }
+
+    #[test]
+    fn replacements_are_paired() {
+        let lines = align(&[mov(1), mov(2), mov(3)], &[mov(1), mov(8), mov(3)]);
+        assert_eq!(lines, vec![same(&mov(1)), DiffLine::Changed {expected : mov(2).text, candidate : mov(8).text}, same(&mov(3))]);
+    }
+
+    #[test]
+    fn long_functions_are_compared_in_order() {
+        let expected = vec![mov(1); 4001];
+        let mut candidate = expected.clone();
+        candidate[2000] = mov(2);
+
+        let lines = align(&expected, &candidate);
+        assert_eq!(lines.len(), 4001);
+        assert_eq!(lines[2000], DiffLine::Changed {expected : mov(1).text, candidate : mov(2).text});
+        assert!(lines.iter().enumerate().all(|(i, line)| i == 2000 || *line == same(&mov(1))));
+    }
+}
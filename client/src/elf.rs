@@ -0,0 +1,275 @@
+// a minimal reader for the 64 bit little endian ELF files that solana programs are built as.  Only the headers,
+// section names and dynamic symbols are read, which is all that hashing and diffing programs need
+
+const ELF_MAGIC : &[u8] = b"\x7fELF";
+const ELFCLASS64 : u8 = 2;
+const ELFDATA2LSB : u8 = 1;
+
+pub const SHT_NOBITS : u32 = 8;
+pub const SHT_DYNSYM : u32 = 11;
+
+const SYMBOL_SIZE : usize = 24;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name : String,
+    pub kind : u32,
+    pub address : u64,
+    pub offset : usize,
+    pub size : usize,
+    pub link : u32
+}
+
+impl Section {
+    /// the bytes of the section in the file, NOBITS sections like .bss have none
+    pub fn data<'a>(&self, elf : &'a [u8]) -> &'a [u8] {
+        if self.kind == SHT_NOBITS {
+            return &[];
+        }
+        &elf[self.offset..self.offset + self.size]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub offset : usize,
+    pub file_size : usize
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name : String,
+    pub value : u64,
+    pub size : u64,
+    pub section_index : u16
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Elf {
+    pub header_size : usize,
+    pub segment_table_end : usize,
+    pub section_table_end : usize,
+    pub segments : Vec<Segment>,
+    pub sections : Vec<Section>
+}
+
+fn read_u16(data : &[u8], offset : usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset.checked_add(2)?)?.try_into().ok()?))
+}
+
+fn read_u32(data : &[u8], offset : usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset.checked_add(4)?)?.try_into().ok()?))
+}
+
+fn read_u64(data : &[u8], offset : usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset.checked_add(8)?)?.try_into().ok()?))
+}
+
+// null terminated string starting at offset
+fn read_str(data : &[u8], offset : usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let end = bytes.iter().position(|b| *b == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+impl Elf {
+
+    /// Returns None if data isn't a 64 bit little endian ELF, or any header points outside of data
+    pub fn parse(data : &[u8]) -> Option<Elf> {
+
+        if !data.starts_with(ELF_MAGIC) || data.get(4) != Some(&ELFCLASS64) || data.get(5) != Some(&ELFDATA2LSB) {
+            return None;
+        }
+
+        let phoff = read_u64(data, 0x20)? as usize;
+        let shoff = read_u64(data, 0x28)? as usize;
+        let header_size = read_u16(data, 0x34)? as usize;
+        let phentsize = read_u16(data, 0x36)? as usize;
+        let phnum = read_u16(data, 0x38)? as usize;
+        let shentsize = read_u16(data, 0x3A)? as usize;
+        let shnum = read_u16(data, 0x3C)? as usize;
+        let shstrndx = read_u16(data, 0x3E)? as usize;
+
+        let mut segments = Vec::with_capacity(phnum);
+        for i in 0..phnum {
+            let header = phoff.checked_add(i.checked_mul(phentsize)?)?;
+            segments.push(Segment {
+                offset : read_u64(data, header.checked_add(0x08)?)? as usize,
+                file_size : read_u64(data, header.checked_add(0x20)?)? as usize
+            });
+        }
+
+        let mut sections = Vec::with_capacity(shnum);
+        let mut name_offsets = Vec::with_capacity(shnum);
+        for i in 0..shnum {
+            let header = shoff.checked_add(i.checked_mul(shentsize)?)?;
+            name_offsets.push(read_u32(data, header)? as usize);
+
+            let section = Section {
+                name : String::new(),
+                kind : read_u32(data, header.checked_add(0x04)?)?,
+                address : read_u64(data, header.checked_add(0x10)?)?,
+                offset : read_u64(data, header.checked_add(0x18)?)? as usize,
+                size : read_u64(data, header.checked_add(0x20)?)? as usize,
+                link : read_u32(data, header.checked_add(0x28)?)?
+            };
+
+            if section.kind != SHT_NOBITS && section.offset.checked_add(section.size)? > data.len() {
+                return None;
+            }
+            sections.push(section);
+        }
+
+        // names are only filled in once every section is read, as the string table is itself a section
+        if let Some(strtab) = sections.get(shstrndx).map(|s| s.data(data)) {
+            for (section, name_offset) in sections.iter_mut().zip(name_offsets) {
+                section.name = read_str(strtab, name_offset).unwrap_or_default();
+            }
+        }
+
+        let elf = Elf {
+            header_size : header_size,
+            segment_table_end : phoff.checked_add(phentsize.checked_mul(phnum)?)?,
+            section_table_end : shoff.checked_add(shentsize.checked_mul(shnum)?)?,
+            segments : segments,
+            sections : sections
+        };
+
+        if elf.file_length()? > data.len() {
+            return None;
+        }
+
+        Some(elf)
+    }
+
+    /// the end of the furthest header table, section or segment, i.e. the size of the file the ELF was read from
+    pub fn file_length(&self) -> Option<usize> {
+
+        let mut end = self.header_size.max(self.segment_table_end).max(self.section_table_end);
+
+        for segment in &self.segments {
+            end = end.max(segment.offset.checked_add(segment.file_size)?);
+        }
+
+        for section in self.sections.iter().filter(|s| s.kind != SHT_NOBITS) {
+            end = end.max(section.offset.checked_add(section.size)?);
+        }
+
+        Some(end)
+    }
+
+    pub fn section(&self, name : &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// the entries of .dynsym, named using the string table it links to
+    pub fn dynamic_symbols(&self, data : &[u8]) -> Vec<Symbol> {
+
+        let dynsym = match self.sections.iter().find(|s| s.kind == SHT_DYNSYM) {
+            Some(section) => section,
+            None => return Vec::new()
+        };
+
+        let strtab = self.sections.get(dynsym.link as usize).map_or(&[][..], |s| s.data(data));
+        let table = dynsym.data(data);
+
+        // the first entry is always the null symbol
+        table.chunks_exact(SYMBOL_SIZE).skip(1).filter_map(|entry| {
+            Some(Symbol {
+                name : read_str(strtab, read_u32(entry, 0)? as usize).unwrap_or_default(),
+                section_index : read_u16(entry, 6)?,
+                value : read_u64(entry, 8)?,
+                size : read_u64(entry, 16)?
+            })
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGBITS : u32 = 1;
+
+    // an ELF header whose program and section header tables are at the given offsets
+    fn header(phoff : u64, phnum : u16, shoff : u64, shnum : u16) -> Vec<u8> {
+        let mut data = vec![0u8; 64];
+        data[..4].copy_from_slice(ELF_MAGIC);
+        data[4] = ELFCLASS64;
+        data[5] = ELFDATA2LSB;
+        data[0x20..0x28].copy_from_slice(&phoff.to_le_bytes());
+        data[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+        data[0x34..0x36].copy_from_slice(&64u16.to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        data[0x38..0x3A].copy_from_slice(&phnum.to_le_bytes());
+        data[0x3A..0x3C].copy_from_slice(&64u16.to_le_bytes());
+        data[0x3C..0x3E].copy_from_slice(&shnum.to_le_bytes());
+        data
+    }
+
+    // a header followed by one section header, for a section holding its own name that is also the string table
+    fn with_section() -> Vec<u8> {
+        let mut data = header(0, 0, 64, 1);
+        let mut section = vec![0u8; 64];
+        section[0x04..0x08].copy_from_slice(&PROGBITS.to_le_bytes());
+        section[0x18..0x20].copy_from_slice(&128u64.to_le_bytes());
+        section[0x20..0x28].copy_from_slice(&5u64.to_le_bytes());
+        data.extend(section);
+        data.extend(b".txt\0");
+        data
+    }
+
+    #[test]
+    fn headers_and_sections_are_read() {
+        let elf = Elf::parse(&header(64, 0, 64, 0)).unwrap();
+        assert!(elf.sections.is_empty());
+        assert_eq!(elf.file_length(), Some(64));
+
+        let data = with_section();
+        let elf = Elf::parse(&data).unwrap();
+        assert_eq!(elf.file_length(), Some(data.len()));
+        assert_eq!(elf.section(".txt").map(|section| section.data(&data)), Some(&b".txt\0"[..]));
+    }
+
+    #[test]
+    fn padding_is_not_part_of_the_file() {
+        let mut data = with_section();
+        let length = data.len();
+        data.resize(length + 100, 0);
+        assert_eq!(Elf::parse(&data).and_then(|elf| elf.file_length()), Some(length));
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let data = with_section();
+        for length in 0..data.len() {
+            assert_eq!(Elf::parse(&data[..length]), None, "parsed the first {} bytes", length);
+        }
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        let mut not_elf = header(64, 0, 64, 0);
+        not_elf[0] = 0;
+        assert_eq!(Elf::parse(&not_elf), None);
+
+        let mut elf32 = header(64, 0, 64, 0);
+        elf32[4] = 1;
+        assert_eq!(Elf::parse(&elf32), None);
+
+        let mut big_endian = header(64, 0, 64, 0);
+        big_endian[5] = 2;
+        assert_eq!(Elf::parse(&big_endian), None);
+    }
+
+    #[test]
+    fn offsets_past_the_end_are_rejected() {
+        assert_eq!(Elf::parse(&header(u64::MAX - 4, 1, 64, 0)), None);
+        assert_eq!(Elf::parse(&header(64, 0, u64::MAX - 4, 1)), None);
+        assert_eq!(Elf::parse(&header(64, u16::MAX, 64, 0)), None);
+
+        let mut data = with_section();
+        data[64 + 0x18..64 + 0x20].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(Elf::parse(&data), None);
+    }
+}
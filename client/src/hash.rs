@@ -0,0 +1,67 @@
+use crate::elf::Elf;
+
+use sha2::{Sha256, Digest};
+
+/// The size of the ELF file at the start of data, or None if data doesn't start with a 64 bit little endian ELF,
+/// which is all that solana programs are built as
+pub fn elf_length(data : &[u8]) -> Option<usize> {
+    Elf::parse(data)?.file_length()
+}
+
+/// The part of a program's bytes that the canonical hash covers.  This is the ELF itself, without any zero padding
+/// left at the end of a program data account by --max-len or `solana program extend`, so a deployed program hashes
+/// the same as the .so it was built from.  Anything that can't be parsed as an ELF just has trailing zeros removed
+pub fn canonical_program_bytes(data : &[u8]) -> &[u8] {
+
+    if let Some(length) = elf_length(data) {
+        return &data[..length];
+    }
+
+    let end = data.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    &data[..end]
+}
+
+/// sha256 of the canonical program bytes, this is what is stored in the verification record
+pub fn canonical_hash(data : &[u8]) -> [u8; 32] {
+    Sha256::digest(canonical_program_bytes(data)).into()
+}
+
+pub fn to_hex(bytes : &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the smallest ELF the parser accepts, a header with no segments or sections
+    fn empty_elf() -> Vec<u8> {
+        let mut data = vec![0u8; 64];
+        data[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        data[0x34] = 64;
+        data
+    }
+
+    #[test]
+    fn elf_padding_is_dropped() {
+        let elf = empty_elf();
+        let mut padded = elf.clone();
+        padded.resize(1024, 0);
+        assert_eq!(canonical_program_bytes(&padded), &elf[..]);
+        assert_eq!(canonical_hash(&padded), canonical_hash(&elf));
+    }
+
+    #[test]
+    fn elf_zeros_are_kept() {
+        // the header ends in zeros, which are part of the ELF rather than padding
+        let elf = empty_elf();
+        assert_eq!(canonical_program_bytes(&elf).len(), 64);
+    }
+
+    #[test]
+    fn other_data_loses_trailing_zeros() {
+        assert_eq!(canonical_program_bytes(&[1, 0, 2, 0, 0]), &[1, 0, 2]);
+        assert_eq!(canonical_program_bytes(&[0, 0, 0]), &[] as &[u8]);
+        assert_eq!(canonical_program_bytes(&[]), &[] as &[u8]);
+    }
+}
@@ -0,0 +1,319 @@
+//! A program's verification history.  Each verification overwrites the program's record, so earlier ones are
+//! recovered from the transactions that wrote it, and laid out next to the deploys and upgrades of the program
+//! so it's clear which deployment each verification covered
+
+use crate::state::{Error, Result, Network, network_to_string};
+use crate::client::{VerifierClient, fetch_signatures};
+use crate::registry::RecordEvent;
+use crate::badge::date;
+
+use serde::Serialize;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
+use solana_program::{loader_upgradeable_instruction::UpgradeableLoaderInstruction, pubkey::Pubkey};
+use solana_sdk::{bpf_loader_upgradeable, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+use std::str::FromStr;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentKind {
+    Deploy,
+    Upgrade,
+    // the upgrade authority was changed, or removed to make the program immutable
+    SetAuthority
+}
+
+/// A transaction that deployed, upgraded or changed the authority of a program
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DeploymentEvent {
+    pub kind : DeploymentKind,
+    pub signature : String,
+    pub slot : u64,
+    pub block_time : Option<i64>,
+    // the authority after the transaction, None once the program is immutable
+    pub authority : Option<String>
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum TimelineEvent {
+    Record(RecordEvent),
+    Deployment(DeploymentEvent)
+}
+
+impl TimelineEvent {
+    pub fn slot(&self) -> u64 {
+        match self {
+            TimelineEvent::Record(event) => event.slot(),
+            TimelineEvent::Deployment(event) => event.slot
+        }
+    }
+
+    pub fn block_time(&self) -> Option<i64> {
+        match self {
+            TimelineEvent::Record(event) => event.block_time(),
+            TimelineEvent::Deployment(event) => event.block_time
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TimelineEntry {
+    #[serde(flatten)]
+    pub event : TimelineEvent,
+    // for verifications, the slot of the deploy or upgrade that was verified, None if it's older than the fetched history
+    pub covers_deployment : Option<u64>,
+    // for verifications, the slot of the first upgrade after the one verified, None if it is still deployed
+    pub superseded_at : Option<u64>
+}
+
+/// The verification history of a program, from history
+#[derive(Serialize, Debug, Clone)]
+pub struct ProgramHistory {
+    pub program : String,
+    pub network : String,
+    pub metadata_account : String,
+    // None for programs that aren't upgradeable, whose deployments can't be found this way
+    pub program_data_account : Option<String>,
+    // oldest first
+    pub timeline : Vec<TimelineEntry>
+}
+
+// the bincode tag of the loader's SetAuthorityChecked instruction, which the loader instruction enum in this
+// version of solana-program doesn't have yet
+const SET_AUTHORITY_CHECKED_TAG : [u8; 4] = [7, 0, 0, 0];
+
+// the deploys, upgrades and authority changes in a transaction on the program data account
+fn deployment_events(transaction : &solana_sdk::transaction::VersionedTransaction, program_data : &Pubkey) -> Vec<(DeploymentKind, Option<Pubkey>)> {
+
+    let keys = transaction.message.static_account_keys();
+    let mut events = Vec::new();
+
+    for instruction in transaction.message.instructions() {
+        if !keys.get(instruction.program_id_index as usize).is_some_and(bpf_loader_upgradeable::check_id) {
+            continue;
+        }
+        let accounts : Vec<Pubkey> = instruction.accounts.iter().filter_map(|index| keys.get(*index as usize).copied()).collect();
+
+        // the program data account and authority are in different places for each instruction
+        let (kind, data_position, authority) = match bincode::deserialize::<UpgradeableLoaderInstruction>(&instruction.data) {
+            Ok(UpgradeableLoaderInstruction::DeployWithMaxDataLen {..}) => (DeploymentKind::Deploy, 1, accounts.get(7).copied()),
+            Ok(UpgradeableLoaderInstruction::Upgrade) => (DeploymentKind::Upgrade, 0, accounts.get(6).copied()),
+            Ok(UpgradeableLoaderInstruction::SetAuthority) => (DeploymentKind::SetAuthority, 0, accounts.get(2).copied()),
+            // SetAuthorityChecked always has a new authority, as it must sign
+            _ if instruction.data == SET_AUTHORITY_CHECKED_TAG => (DeploymentKind::SetAuthority, 0, accounts.get(2).copied()),
+            _ => continue
+        };
+        if accounts.get(data_position) == Some(program_data) {
+            events.push((kind, authority));
+        }
+    }
+
+    events
+}
+
+/// Every deploy, upgrade and authority change of an upgradeable program, oldest first
+pub async fn fetch_deployments(rpc : &RpcClient, program : &Pubkey) -> Result<Vec<DeploymentEvent>> {
+
+    let program_data = Pubkey::find_program_address(&[program.as_ref()], &bpf_loader_upgradeable::id()).0;
+
+    let mut deployments = Vec::new();
+    for status in fetch_signatures(rpc, &program_data, None).await?.iter().rev().filter(|status| status.err.is_none()) {
+
+        let signature = Signature::from_str(&status.signature).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+        let config = RpcTransactionConfig {encoding : Some(UiTransactionEncoding::Base64), commitment : None, max_supported_transaction_version : Some(0)};
+        let confirmed = rpc.get_transaction_with_config(&signature, config).await?;
+        let transaction = match confirmed.transaction.transaction.decode() {
+            Some(transaction) => transaction,
+            None => continue
+        };
+
+        for (kind, authority) in deployment_events(&transaction, &program_data) {
+            deployments.push(DeploymentEvent {
+                kind : kind,
+                signature : status.signature.clone(),
+                slot : confirmed.slot,
+                block_time : confirmed.block_time,
+                authority : authority.map(|authority| authority.to_string())
+            });
+        }
+    }
+
+    Ok(deployments)
+}
+
+// entries paired with their block time, or for those without one the time of the entry before them
+fn with_times(entries : Vec<TimelineEntry>) -> Vec<(i64, TimelineEntry)> {
+    let mut last = i64::MIN;
+    entries.into_iter().map(|entry| {
+        last = entry.event.block_time().unwrap_or(last);
+        (last, entry)
+    }).collect()
+}
+
+// records are read from the verifier's cluster and deployments from the program's, so their slots can't be compared.
+// Each is put in slot order and the two are merged by block time, with a deployment first when the times are equal
+fn merge_by_time(mut records : Vec<TimelineEntry>, mut deployments : Vec<TimelineEntry>) -> Vec<TimelineEntry> {
+
+    records.sort_by_key(|entry| entry.event.slot());
+    deployments.sort_by_key(|entry| entry.event.slot());
+
+    let mut timeline = Vec::with_capacity(records.len() + deployments.len());
+    let mut records = with_times(records).into_iter().peekable();
+    let mut deployments = with_times(deployments).into_iter().peekable();
+
+    loop {
+        let next = match (records.peek(), deployments.peek()) {
+            (Some((record_time, _)), Some((deployment_time, _))) if deployment_time <= record_time => deployments.next(),
+            (Some(_), _) => records.next(),
+            (None, _) => deployments.next()
+        };
+        match next {
+            Some((_, entry)) => timeline.push(entry),
+            None => return timeline
+        }
+    }
+}
+
+impl ProgramHistory {
+    pub fn print(&self) {
+        println!("history of {} on {}", self.program, self.network);
+        println!("record account: {}", self.metadata_account);
+        match &self.program_data_account {
+            Some(account) => println!("program data account: {}", account),
+            None => println!("the program isn't upgradeable, so only its verifications are shown")
+        }
+
+        for entry in self.timeline.iter() {
+            let when = entry.event.block_time().map(date).unwrap_or_else(|| "          ".to_string());
+
+            match &entry.event {
+                TimelineEvent::Deployment(event) => {
+                    let authority = event.authority.as_deref().unwrap_or("none, immutable");
+                    let kind = match event.kind {
+                        DeploymentKind::Deploy => "deployed",
+                        DeploymentKind::Upgrade => "upgraded",
+                        DeploymentKind::SetAuthority => "authority set"
+                    };
+                    println!("slot {:>10}  {}  {}, authority {}  {}", event.slot, when, kind, authority, event.signature);
+                },
+                TimelineEvent::Record(RecordEvent::Submission {slot, signature, git_repo, git_commit, user, ..}) => {
+                    println!("slot {:>10}  {}  submitted {} {} by {}  {}", slot, when, git_repo, git_commit, user.as_deref().unwrap_or("unknown"), signature);
+                },
+                TimelineEvent::Record(RecordEvent::Verification {slot, signature, verified_code, description, data_hash, ..}) => {
+                    println!("slot {:>10}  {}  verified code {} ({}) hash {}  {}", slot, when, verified_code, description, data_hash, signature);
+                    match (entry.covers_deployment, entry.superseded_at) {
+                        (Some(covered), Some(superseded)) => println!("{:28}covers the deployment at slot {}, upgraded again at slot {}", "", covered, superseded),
+                        (Some(covered), None) => println!("{:28}covers the deployment at slot {}, which is still deployed", "", covered),
+                        (None, _) => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl VerifierClient {
+
+    /// Every submission and verification of a program, and its deploys and upgrades, in order of block time
+    pub async fn program_history(&self, program : &Pubkey, network : Network) -> Result<ProgramHistory> {
+
+        let metadata_key = self.metadata_address(program, network);
+
+        let mut records = Vec::new();
+        for status in fetch_signatures(self.rpc(), &metadata_key, None).await?.iter().rev().filter(|status| status.err.is_none()) {
+            let signature = Signature::from_str(&status.signature).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+            if let Some(transaction) = self.verifier_transaction(&signature).await? {
+                records.extend(transaction.record_events(program, network));
+            }
+        }
+
+        let program_client = self.program_client(network);
+        let upgradeable = program_client.get_account(program).await.is_ok_and(|account| bpf_loader_upgradeable::check_id(&account.owner));
+        let deployments = if upgradeable { fetch_deployments(&program_client, program).await? } else { Vec::new() };
+
+        // deploys and upgrades, leaving out authority changes, as those don't change the code
+        let code_changes : Vec<u64> = deployments.iter().filter(|event| event.kind != DeploymentKind::SetAuthority).map(|event| event.slot).collect();
+
+        let records : Vec<TimelineEntry> = records.into_iter().map(|event| {
+            // the slot recorded with a verification is the slot the program was read at, so the verified code is the
+            // last deploy or upgrade at or before it
+            let (covers_deployment, superseded_at) = match &event {
+                RecordEvent::Verification {verified_slot, ..} if upgradeable => {
+                    (code_changes.iter().rev().copied().find(|slot| slot <= verified_slot), code_changes.iter().copied().find(|slot| slot > verified_slot))
+                },
+                _ => (None, None)
+            };
+            TimelineEntry {event : TimelineEvent::Record(event), covers_deployment : covers_deployment, superseded_at : superseded_at}
+        }).collect();
+
+        let deployments = deployments.into_iter().map(|event| TimelineEntry {event : TimelineEvent::Deployment(event), covers_deployment : None, superseded_at : None}).collect();
+        let timeline = merge_by_time(records, deployments);
+
+        Ok(ProgramHistory {
+            program : program.to_string(),
+            network : network_to_string(network),
+            metadata_account : metadata_key.to_string(),
+            program_data_account : if upgradeable {
+                Some(Pubkey::find_program_address(&[program.as_ref()], &bpf_loader_upgradeable::id()).0.to_string())
+            } else {
+                None
+            },
+            timeline : timeline
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submission(slot : u64, block_time : Option<i64>) -> TimelineEntry {
+        let event = RecordEvent::Submission {
+            signature : format!("submission {}", slot),
+            slot : slot,
+            block_time : block_time,
+            user : None,
+            git_repo : "https://github.com/daoplays/sol_verify".to_string(),
+            git_commit : String::new(),
+            directory : String::new()
+        };
+        TimelineEntry {event : TimelineEvent::Record(event), covers_deployment : None, superseded_at : None}
+    }
+
+    fn deployment(slot : u64, block_time : Option<i64>) -> TimelineEntry {
+        let event = DeploymentEvent {kind : DeploymentKind::Upgrade, signature : format!("deployment {}", slot), slot : slot, block_time : block_time, authority : None};
+        TimelineEntry {event : TimelineEvent::Deployment(event), covers_deployment : None, superseded_at : None}
+    }
+
+    fn signatures(timeline : &[TimelineEntry]) -> Vec<String> {
+        timeline.iter().map(|entry| match &entry.event {
+            TimelineEvent::Record(RecordEvent::Submission {signature, ..} | RecordEvent::Verification {signature, ..}) => signature.clone(),
+            TimelineEvent::Deployment(event) => event.signature.clone()
+        }).collect()
+    }
+
+    #[test]
+    fn clusters_are_merged_by_block_time() {
+        // the verifier's cluster is far ahead in slots of the program's, so ordering by slot would put every deployment first
+        let records = vec![submission(200_000, Some(300)), submission(100_000, Some(100))];
+        let deployments = vec![deployment(20, Some(400)), deployment(10, Some(200))];
+
+        assert_eq!(signatures(&merge_by_time(records, deployments)), vec!["submission 100000", "deployment 10", "submission 200000", "deployment 20"]);
+    }
+
+    #[test]
+    fn deployments_come_first_at_the_same_time() {
+        let timeline = merge_by_time(vec![submission(100_000, Some(100))], vec![deployment(10, Some(100))]);
+        assert_eq!(signatures(&timeline), vec!["deployment 10", "submission 100000"]);
+    }
+
+    #[test]
+    fn entries_without_a_time_follow_the_one_before() {
+        let records = vec![submission(100_000, Some(100)), submission(100_001, None), submission(200_000, Some(300))];
+        let deployments = vec![deployment(5, None), deployment(10, Some(200))];
+
+        assert_eq!(signatures(&merge_by_time(records, deployments)),
+            vec!["deployment 5", "submission 100000", "submission 100001", "deployment 10", "submission 200000"]);
+    }
+}
@@ -0,0 +1,397 @@
+//! A local SQLite index of everything the sol_verify program has recorded.  Syncing replays the program's
+//! transactions, oldest first, into tables of submissions, verifications and status updates, then reads the current
+//! state of every record and user account those transactions touched.  Each sync starts after the last signature
+//! the previous one reached, so an interrupted sync picks up where it stopped
+
+use crate::state::{Error, Result, Network, ProgramMetaData, UserMetaData, VerifyInstruction, network_to_string};
+use crate::client::{VerifierClient, fetch_signatures};
+use crate::registry::{RecordEntry, RecordEvent, UserEntry};
+use crate::hash::to_hex;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::str::FromStr;
+
+pub const DEFAULT_INDEX_PATH : &str = "sol_verify_index.db";
+
+// the most accounts the rpc returns in one call
+const ACCOUNT_BATCH_LIMIT : usize = 100;
+
+// records and users are flagged for refresh as transactions are replayed, and read back once they all have been
+const SCHEMA : &str = "
+    CREATE TABLE IF NOT EXISTS sync (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        program_id TEXT NOT NULL,
+        last_signature TEXT,
+        last_slot INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS submissions (
+        signature TEXT NOT NULL,
+        instruction INTEGER NOT NULL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        user TEXT,
+        program TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        git_repo TEXT NOT NULL,
+        git_commit TEXT NOT NULL,
+        directory TEXT NOT NULL,
+        docker_version TEXT NOT NULL,
+        rust_version TEXT NOT NULL,
+        solana_version TEXT NOT NULL,
+        anchor_version TEXT NOT NULL,
+        PRIMARY KEY (signature, instruction)
+    );
+    CREATE TABLE IF NOT EXISTS verifications (
+        signature TEXT NOT NULL,
+        instruction INTEGER NOT NULL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        program TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        test_address TEXT NOT NULL,
+        verified_code INTEGER NOT NULL,
+        data_hash TEXT NOT NULL,
+        verified_slot INTEGER NOT NULL,
+        git_repo TEXT NOT NULL,
+        git_commit TEXT NOT NULL,
+        directory TEXT NOT NULL,
+        PRIMARY KEY (signature, instruction)
+    );
+    CREATE TABLE IF NOT EXISTS status_updates (
+        signature TEXT NOT NULL,
+        instruction INTEGER NOT NULL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        user TEXT NOT NULL,
+        status_code INTEGER NOT NULL,
+        stage TEXT NOT NULL,
+        progress INTEGER,
+        message TEXT NOT NULL,
+        PRIMARY KEY (signature, instruction)
+    );
+    CREATE TABLE IF NOT EXISTS records (
+        record_account TEXT PRIMARY KEY,
+        program TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        verified_code INTEGER,
+        data_hash TEXT,
+        git_repo TEXT,
+        git_commit TEXT,
+        directory TEXT,
+        last_verified_slot INTEGER,
+        test_address TEXT,
+        refresh INTEGER NOT NULL DEFAULT 1
+    );
+    CREATE TABLE IF NOT EXISTS users (
+        user_account TEXT PRIMARY KEY,
+        user TEXT NOT NULL,
+        status_code INTEGER,
+        stage TEXT,
+        progress INTEGER,
+        message TEXT,
+        timestamp INTEGER,
+        refresh INTEGER NOT NULL DEFAULT 1
+    );
+    CREATE INDEX IF NOT EXISTS submissions_by_program ON submissions (program, cluster);
+    CREATE INDEX IF NOT EXISTS verifications_by_program ON verifications (program, cluster);
+    CREATE INDEX IF NOT EXISTS status_updates_by_user ON status_updates (user);
+    CREATE INDEX IF NOT EXISTS records_by_repo ON records (git_repo);
+";
+
+/// What a sync added to the index
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SyncReport {
+    pub signatures : usize,
+    pub submissions : usize,
+    pub verifications : usize,
+    pub status_updates : usize,
+    // record and user accounts read back after the transactions were replayed
+    pub records_refreshed : usize,
+    pub users_refreshed : usize,
+    // "signature#position" of each sol_verify instruction that couldn't be decoded, so wasn't indexed
+    pub undecoded_instructions : Vec<String>,
+    // accounts left flagged for the next sync because they were missing or couldn't be read
+    pub unreadable_records : Vec<String>,
+    pub unreadable_users : Vec<String>,
+    pub last_signature : Option<String>
+}
+
+/// Which records to return from the index.  Anything left as None matches every record
+#[derive(Debug, Clone, Default)]
+pub struct RecordQuery {
+    pub program : Option<String>,
+    pub git_repo : Option<String>,
+    pub cluster : Option<Network>,
+    pub verified_code : Option<u8>
+}
+
+pub struct VerificationIndex {
+    connection : Connection
+}
+
+impl VerificationIndex {
+
+    /// Opens the index at path, creating it if needed.  An index only ever holds the data of one sol_verify program
+    pub fn open(path : &str, program_id : &Pubkey) -> Result<VerificationIndex> {
+
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+
+        let indexed : Option<String> = connection.query_row("SELECT program_id FROM sync WHERE id = 0", [], |row| row.get(0)).optional()?;
+        match indexed {
+            Some(indexed) if indexed != program_id.to_string() =>
+                return Err(Error::InvalidConfig(format!("{} indexes program {}, not {}", path, indexed, program_id))),
+            Some(_) => {},
+            None => {
+                connection.execute("INSERT INTO sync (id, program_id) VALUES (0, ?1)", params![program_id.to_string()])?;
+            }
+        }
+
+        Ok(VerificationIndex {connection : connection})
+    }
+
+    /// The newest signature that has been replayed, None if nothing has been
+    pub fn last_signature(&self) -> Result<Option<String>> {
+        Ok(self.connection.query_row("SELECT last_signature FROM sync WHERE id = 0", [], |row| row.get(0))?)
+    }
+
+    /// Replays every sol_verify transaction since the last sync, then refreshes the accounts they touched
+    pub async fn sync(&mut self, client : &VerifierClient) -> Result<SyncReport> {
+
+        let mut report = SyncReport::default();
+
+        let until = match self.last_signature()? {
+            Some(signature) => Some(Signature::from_str(&signature).map_err(|err| Error::InvalidConfig(err.to_string()))?),
+            None => None
+        };
+
+        let signatures = fetch_signatures(client.rpc(), &client.program_id(), until).await?;
+
+        for status in signatures.iter().rev() {
+
+            // failed transactions changed nothing, but still move the sync forward
+            let transaction = if status.err.is_none() {
+                let signature = Signature::from_str(&status.signature).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+                client.verifier_transaction(&signature).await?
+            }
+            else {
+                None
+            };
+
+            let db = self.connection.transaction()?;
+
+            if let Some(transaction) = transaction {
+                report.undecoded_instructions.extend(transaction.undecoded.iter().map(|position| format!("{}#{}", transaction.signature, position)));
+                for (index, (instruction, accounts)) in transaction.instructions.iter().enumerate() {
+                    match instruction {
+                        VerifyInstruction::SubmitProgram {metadata} => {
+                            db.execute("INSERT OR IGNORE INTO submissions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)", params![
+                                transaction.signature, index, transaction.slot, transaction.block_time, accounts.first().map(|user| user.to_string()),
+                                metadata.address.to_string(), network_to_string(metadata.network), metadata.git_repo, metadata.git_commit,
+                                metadata.directory, metadata.docker_version, metadata.rust_version, metadata.solana_version, metadata.anchor_version
+                            ])?;
+                            mark_record(&db, client, &metadata.address, metadata.network)?;
+                            if let Some(user) = accounts.first() {
+                                mark_user(&db, client, user)?;
+                            }
+                            report.submissions += 1;
+                        },
+                        VerifyInstruction::VerifyProgram {metadata} => {
+                            db.execute("INSERT OR IGNORE INTO verifications VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)", params![
+                                transaction.signature, index, transaction.slot, transaction.block_time, metadata.real_address.to_string(),
+                                network_to_string(metadata.network), metadata.test_address.to_string(), metadata.verified_code,
+                                to_hex(&metadata.data_hash), metadata.verified_slot, metadata.git_repo, metadata.git_commit, metadata.directory
+                            ])?;
+                            mark_record(&db, client, &metadata.real_address, metadata.network)?;
+                            report.verifications += 1;
+                        },
+                        VerifyInstruction::UpdateStatus {metadata} => {
+                            db.execute("INSERT OR IGNORE INTO status_updates VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)", params![
+                                transaction.signature, index, transaction.slot, transaction.block_time, metadata.user_pubkey.to_string(),
+                                metadata.status_code, metadata.stage.name(), metadata.progress, metadata.log_message
+                            ])?;
+                            mark_user(&db, client, &metadata.user_pubkey)?;
+                            report.status_updates += 1;
+                        }
+                    }
+                }
+            }
+
+            db.execute("UPDATE sync SET last_signature = ?1, last_slot = ?2 WHERE id = 0", params![status.signature, status.slot])?;
+            db.commit()?;
+
+            report.signatures += 1;
+            report.last_signature = Some(status.signature.clone());
+        }
+
+        (report.records_refreshed, report.unreadable_records) = self.refresh_records(client).await?;
+        (report.users_refreshed, report.unreadable_users) = self.refresh_users(client).await?;
+
+        Ok(report)
+    }
+
+    // reads back every record flagged for refresh, returning how many were and the addresses of any that couldn't be
+    async fn refresh_records(&mut self, client : &VerifierClient) -> Result<(usize, Vec<String>)> {
+
+        let pending = pending_accounts(&self.connection, "SELECT record_account FROM records WHERE refresh = 1")?;
+        let mut unreadable = Vec::new();
+
+        for batch in pending.chunks(ACCOUNT_BATCH_LIMIT) {
+            let accounts = client.rpc().get_multiple_accounts(batch).await?;
+
+            let db = self.connection.transaction()?;
+            for (address, account) in batch.iter().zip(accounts) {
+                // a record that can't be read yet is left flagged for the next sync
+                let record = match account.map(|account| ProgramMetaData::unpack(&account.data)) {
+                    Some(Ok(record)) => record,
+                    _ => {
+                        unreadable.push(address.to_string());
+                        continue;
+                    }
+                };
+                let entry = RecordEntry::new(address, None, &record, None);
+                db.execute("UPDATE records SET verified_code = ?2, data_hash = ?3, git_repo = ?4, git_commit = ?5, directory = ?6,
+                        last_verified_slot = ?7, test_address = ?8, refresh = 0 WHERE record_account = ?1", params![
+                    entry.record_account, entry.verified_code, entry.data_hash, entry.git_repo, entry.git_commit, entry.directory,
+                    entry.last_verified_slot, entry.test_address
+                ])?;
+            }
+            db.commit()?;
+        }
+
+        Ok((pending.len() - unreadable.len(), unreadable))
+    }
+
+    // reads back every user account flagged for refresh, returning how many were and the addresses of any that couldn't be
+    async fn refresh_users(&mut self, client : &VerifierClient) -> Result<(usize, Vec<String>)> {
+
+        let pending = pending_accounts(&self.connection, "SELECT user_account FROM users WHERE refresh = 1")?;
+        let mut unreadable = Vec::new();
+
+        for batch in pending.chunks(ACCOUNT_BATCH_LIMIT) {
+            let accounts = client.rpc().get_multiple_accounts(batch).await?;
+
+            let db = self.connection.transaction()?;
+            for (address, account) in batch.iter().zip(accounts) {
+                let user_data = match account.map(|account| UserMetaData::unpack(&account.data)) {
+                    Some(Ok(user_data)) => user_data,
+                    _ => {
+                        unreadable.push(address.to_string());
+                        continue;
+                    }
+                };
+                let entry = UserEntry::new(address, None, &user_data);
+                db.execute("UPDATE users SET status_code = ?2, stage = ?3, progress = ?4, message = ?5, timestamp = ?6, refresh = 0
+                        WHERE user_account = ?1", params![
+                    entry.user_account, entry.status_code, entry.stage.name(), entry.progress, entry.message, entry.timestamp
+                ])?;
+            }
+            db.commit()?;
+        }
+
+        Ok((pending.len() - unreadable.len(), unreadable))
+    }
+
+    /// The indexed records matching query, as of the last sync.  Staleness isn't indexed, so stale is always None
+    pub fn records(&self, query : &RecordQuery) -> Result<Vec<RecordEntry>> {
+
+        let mut statement = self.connection.prepare(
+            "SELECT record_account, program, cluster, verified_code, data_hash, git_repo, git_commit, directory, last_verified_slot, test_address
+             FROM records
+             WHERE verified_code IS NOT NULL
+               AND (?1 IS NULL OR program = ?1)
+               AND (?2 IS NULL OR git_repo = ?2)
+               AND (?3 IS NULL OR cluster = ?3)
+               AND (?4 IS NULL OR verified_code = ?4)
+             ORDER BY program, cluster")?;
+
+        let rows = statement.query_map(params![query.program, query.git_repo, query.cluster.map(network_to_string), query.verified_code], |row| {
+            let verified_code : u8 = row.get(3)?;
+            Ok(RecordEntry {
+                record_account : row.get(0)?,
+                program : row.get(1)?,
+                cluster : row.get(2)?,
+                verified_code : verified_code,
+                description : crate::state::verified_code_description(verified_code).to_string(),
+                data_hash : row.get(4)?,
+                git_repo : row.get(5)?,
+                git_commit : row.get(6)?,
+                directory : row.get(7)?,
+                last_verified_slot : row.get(8)?,
+                test_address : row.get(9)?,
+                stale : None
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<Vec<RecordEntry>>>()?)
+    }
+
+    /// Every indexed submission and verification of program on network, oldest first
+    pub fn record_events(&self, program : &Pubkey, network : Network) -> Result<Vec<RecordEvent>> {
+
+        let mut statement = self.connection.prepare(
+            "SELECT slot, instruction, signature, block_time, user, NULL, NULL, NULL, NULL, git_repo, git_commit, directory
+             FROM submissions WHERE program = ?1 AND cluster = ?2
+             UNION ALL
+             SELECT slot, instruction, signature, block_time, NULL, verified_code, data_hash, verified_slot, test_address, git_repo, git_commit, directory
+             FROM verifications WHERE program = ?1 AND cluster = ?2
+             ORDER BY 1, 2")?;
+
+        let rows = statement.query_map(params![program.to_string(), network_to_string(network)], |row| {
+            let verified_code : Option<u8> = row.get(5)?;
+            Ok(match verified_code {
+                None => RecordEvent::Submission {
+                    signature : row.get(2)?,
+                    slot : row.get(0)?,
+                    block_time : row.get(3)?,
+                    user : row.get(4)?,
+                    git_repo : row.get(9)?,
+                    git_commit : row.get(10)?,
+                    directory : row.get(11)?
+                },
+                Some(verified_code) => RecordEvent::Verification {
+                    signature : row.get(2)?,
+                    slot : row.get(0)?,
+                    block_time : row.get(3)?,
+                    verified_code : verified_code,
+                    description : crate::state::verified_code_description(verified_code).to_string(),
+                    data_hash : row.get(6)?,
+                    verified_slot : row.get(7)?,
+                    test_address : row.get(8)?,
+                    git_repo : row.get(9)?,
+                    git_commit : row.get(10)?,
+                    directory : row.get(11)?
+                }
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<Vec<RecordEvent>>>()?)
+    }
+}
+
+// flags the record of program on network for refresh, adding it if it's new
+fn mark_record(db : &Connection, client : &VerifierClient, program : &Pubkey, network : Network) -> Result<()> {
+    db.execute("INSERT INTO records (record_account, program, cluster) VALUES (?1, ?2, ?3) ON CONFLICT (record_account) DO UPDATE SET refresh = 1",
+        params![client.metadata_address(program, network).to_string(), program.to_string(), network_to_string(network)])?;
+    Ok(())
+}
+
+// flags the account of user for refresh, adding it if it's new
+fn mark_user(db : &Connection, client : &VerifierClient, user : &Pubkey) -> Result<()> {
+    db.execute("INSERT INTO users (user_account, user) VALUES (?1, ?2) ON CONFLICT (user_account) DO UPDATE SET refresh = 1",
+        params![client.user_address(user).to_string(), user.to_string()])?;
+    Ok(())
+}
+
+fn pending_accounts(db : &Connection, query : &str) -> Result<Vec<Pubkey>> {
+
+    let mut statement = db.prepare(query)?;
+    let addresses = statement.query_map([], |row| row.get::<_, String>(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+
+    addresses.iter()
+        .map(|address| Pubkey::from_str(address).map_err(|err| Error::InvalidConfig(format!("bad account {} in index: {}", address, err))))
+        .collect()
+}
@@ -0,0 +1,26 @@
+//! Submit programs to the sol_verify program, verify them, and read back the results.  VerifierClient does
+//! everything that needs the network, asynchronously, and the other modules work on program bytes alone
+// struct initialisers name every field, as in `address : address`, throughout the client
+#![allow(clippy::redundant_field_names)]
+
+pub mod state;
+pub mod manifest;
+pub mod hash;
+pub mod elf;
+pub mod diff;
+pub mod disasm;
+pub mod toolchain;
+pub mod security;
+pub mod revision;
+pub mod output;
+pub mod client;
+pub mod registry;
+pub mod index;
+pub mod badge;
+pub mod decode;
+pub mod history;
+pub mod simulate;
+pub mod api;
+
+pub use crate::client::{VerifierClient, ClusterConfig, VerifyRequest};
+pub use crate::state::{Error, Result};
@@ -1,590 +1,352 @@
-
-pub mod state;
-
-use crate::state::{Result, SubmitProgramMeta, VerifyInstruction, VerifyProgramMeta, ProgramMetaData, StatusMeta, Network, ProgramJsonData};
-
-use std::borrow::Borrow;
-use std::{env, io::BufRead};
+// struct initialisers name every field, as in `address : address`, throughout the client
+#![allow(clippy::redundant_field_names)]
+
+pub mod cli;
+
+use sol_verify_client::state::{self, Error, Result, Network, VerificationStage, network_to_string};
+use sol_verify_client::manifest::SubmitManifest;
+use sol_verify_client::diff::ProgramDiff;
+use sol_verify_client::disasm::{self, DisassemblyDiff};
+use sol_verify_client::toolchain::ToolchainInfo;
+use sol_verify_client::security::SecurityReport;
+use sol_verify_client::client::{fetch_program_elf, VerifyRequest};
+use sol_verify_client::registry::{ScanAccounts, ExportFormat};
+use sol_verify_client::index::{VerificationIndex, RecordQuery};
+use sol_verify_client::badge::BadgeFormat;
+use sol_verify_client::output::{emit, ErrorReport, HashReport, LocalVerifyReport, DiffReport, ToolchainReport, BadgeReport, DryRunReport, ExportReport};
+use sol_verify_client::hash::{canonical_hash, canonical_program_bytes, to_hex};
+use sol_verify_client::revision::{pin_source, PinnedSource};
+use crate::cli::{Cli, ClientConfig, Command, IndexAction, OutputFormat, SendArgs};
+
+use clap::Parser;
 use std::str::FromStr;
-use solana_client::rpc_client::RpcClient;
-use solana_program::{pubkey::Pubkey, rent, native_token::LAMPORTS_PER_SOL, system_program};
-use solana_sdk::{
-    signer::Signer,
-    instruction::{AccountMeta, Instruction},
-    transaction::Transaction, signer::keypair::read_keypair_file, hash,
-    bpf_loader_upgradeable::{self, UpgradeableLoaderState}
-};
-use borsh::{BorshDeserialize, BorshSerialize};
-use solana_transaction_status::UiTransactionEncoding;
-use twoway::find_bytes;
-use sha2::{Sha256, Digest};
-use solana_security_txt::security_txt;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::{Signer, keypair::read_keypair_file};
 use std::fs::File;
 use std::io::prelude::*;
-use serde::{Deserialize, Serialize};
 
-// some globals
-const PROGRAM_KEY : &str = "CNd6wN4en9Xvbf2e1ubb2YyCkC7J1BbbuhAGhqcdHFbi";
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let output = cli.config.output;
 
-const SOLANA_TEST: &str = "https://api.testnet.solana.com";
-const SOLANA_DEV: &str = "https://api.devnet.solana.com";
-const SOLANA_MAIN: &str = "https://api.mainnet-beta.solana.com";
+    let config = match cli.config.resolve() {
+        Ok(config) => config,
+        Err(err) => exit_with_error(output, err, 2)
+    };
 
+    let result = match cli.command {
+        Command::Submit(args) => {
+            let send = args.send.clone();
+            match args.resolve() {
+                Ok(manifest) => submit_program(&config, manifest, &send).await,
+                Err(err) => Err(err)
+            }
+        },
 
-const URL: &str = SOLANA_DEV;
+        Command::Verify {test_keypair, address, network, user, git_repo, git_commit, directory, send} =>
+            verify_program(&config, &test_keypair, address, network, user, git_repo, git_commit, directory, &send).await,
 
-fn u8_to_network(index :  u8) ->  Network 
-{
-    match index {
-        0 => Network::TestNet,
-        1 => Network::DevNet,
-        2 => Network::MainNet,
-        _ => Network::Invalid
-    }
-}
+        Command::VerifyLocal {address, so_path} => verify_local(&config, address, &so_path).await,
 
-pub fn network_to_string(network :  Network) ->  String 
-{
-    match network {
-        Network::TestNet => "test_net".to_string(),
-        Network::DevNet => "dev_net".to_string(),
-        Network::MainNet => "main_net".to_string(),
-        Network::Invalid => "invalid".to_string()
+        Command::Hash {target} => hash_program(&config, &target).await,
 
-    }
-}
+        Command::Diff {expected, candidate} => diff_programs(&config, &expected, &candidate).await,
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let key_file = &args[1];
-    let function = &args[2];
+        Command::DisasmDiff {expected, candidate} => disasm_diff_programs(&config, &expected, &candidate).await,
 
-    if function == "submit" {
+        Command::Toolchain {target} => show_toolchain(&config, &target).await,
 
-        if let Err(err) = submit_program(key_file) {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
-        }
-    }
+        Command::Status {user} => check_status(&config, user).await,
 
-    if function == "verify" {
-
-        let test_key_file = &args[3];
-        let real_address = &args[4];
-        let network_arg = &args[5];
-        let network_u8: u8 = network_arg.parse().unwrap();
-        let network = u8_to_network(network_u8);
-        let user_address = &args[6];
-        let git_repo = &args[7];
-        let git_commit = &args[8];
-        let directory = &args[9];
-
-        if network == Network::Invalid {
-            println!("invalid network");
-            std::process::exit(1);
-        }
-
-        if let Err(err) = verify_program(key_file, test_key_file, real_address, network, user_address, git_repo, git_commit, directory) {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
-        }
-    }
+        Command::Show {address, cluster} => check_metadata(&config, address, cluster).await,
 
-    if function == "check_metadata" {
+        Command::UpdateStatus {user, status_code, message, stage, progress, send} =>
+            update_status(&config, user, status_code, stage, progress, &message, &send).await,
 
-        if let Err(err) = check_metadata() {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
-        }
-    }
+        Command::SecurityTxt {target, json_file} => get_security(&config, &target, json_file.as_deref()).await,
 
-    if function == "update_status" {
+        Command::PinSource {address, git_repo, git_commit, directory} => pin_program_source(&config, address, git_repo, git_commit, directory).await,
 
-        let user_address = &args[3];
-        let status_code_string = &args[4];
-        let log_message = &args[5];
+        Command::Scan {accounts, format, out, skip_staleness} => scan_registry(&config, accounts, format, out.as_deref(), !skip_staleness).await,
 
-        let status_code : u8 = status_code_string.parse().unwrap();
+        Command::History {address, cluster} => show_history(&config, address, cluster).await,
 
+        Command::DecodeTx {signature} => decode_transaction(&config, &signature).await,
 
-        if let Err(err) = update_status(key_file, user_address, status_code, log_message) {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
-        }
-    }
+        Command::Badge {address, cluster, format, out} => write_badge(&config, address, cluster, format, &out).await,
 
-    if function == "write_security" {
+        Command::Index {db, action : IndexAction::Sync} => sync_index(&config, &db).await,
 
-        let program_address = &args[3];
-        if let Err(err) = get_security(program_address) {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
-        }
+        Command::Index {db, action : IndexAction::Records {repo, cluster, verified_code, format, out}} =>
+            query_index(&config, &db, RecordQuery {program : None, git_repo : repo, cluster : cluster, verified_code : verified_code}, format, out.as_deref())
+    };
 
+    if let Err(err) = result {
+        exit_with_error(output, err, 1);
     }
-
-
-
 }
 
-// create a sha256 hash from our initial seed and a nonce value to produce 4 64bit random numbers
-fn get_sha256_hashed_data(real_data : &[u8], test_data: &[u8]) -> (bool, [u8; 32]) {
-
-    let mut real_hasher = Sha256::new();
-    real_hasher.update(real_data);
+fn exit_with_error(format : OutputFormat, err : Error, exit_code : i32) -> ! {
+    // nothing more can be reported if the error itself can't be printed
+    let _ = emit(format, &ErrorReport {error : err.to_string(), exit_code : exit_code, status_code : err.status_code()});
+    std::process::exit(exit_code);
+}
 
-    let real_result = real_hasher.finalize();
+async fn submit_program(config : &ClientConfig, manifest : SubmitManifest, send : &SendArgs) ->Result<()> {
 
-    let mut test_hasher = Sha256::new();
-    test_hasher.update(test_data);
+    let wallet = config.keypair()?;
+    let client = config.sending_client(send)?;
+    let report = client.submit(&wallet, manifest).await?;
 
-    let test_result = test_hasher.finalize();
+    if send.dry_run {
+        return emit(config.output, &DryRunReport {command : "submit".to_string(), simulations : client.take_simulations()});
+    }
+    emit(config.output, &report)
+}
 
-    println!("real: {:?}", real_result);
-    println!("test: {:?}", test_result);
+// a target that names an existing file is read directly, otherwise it is taken as the address of a deployed program
+async fn load_program_target(config : &ClientConfig, target : &str) -> Result<Vec<u8>> {
 
-    let mut hashed_array : [u8; 32] = [0; 32];
-    for i in 0..32 {
-        let hash_slice = &test_result[i..(i+1)];
-        hashed_array[i] = u8::from_le_bytes(hash_slice.try_into().expect("slice with incorrect length"));
+    if std::path::Path::new(target).exists() {
+        return std::fs::read(target).map_err(|err| Error::FileReadError(target.to_string(), err));
     }
 
-    
-    return (real_result == test_result, hashed_array);
-    
+    let address = Pubkey::from_str(target)
+        .map_err(|_| Error::InvalidConfig(format!("{} is neither a file nor a program address", target)))?;
+    fetch_program_elf(config.verifier_client().rpc(), &address).await
 }
 
+async fn hash_program(config : &ClientConfig, target : &str) ->Result<()> {
 
-fn submit_program(key_file: &String) ->Result<()> {
-
-    // (2) Create a new Keypair for the new account
-    let wallet = read_keypair_file(key_file).unwrap();
+    let data = load_program_target(config, target).await?;
 
-    // (3) Create RPC client to be used to talk to Solana cluster
-    let client = RpcClient::new(URL);
+    let program_bytes = canonical_program_bytes(&data);
 
-    let real_address = Pubkey::from_str("6Eur9ULgxmgcK2Lk8vdCyasBLmU5HzYgmCxsYunr7U52").unwrap();
-    let network = Network::DevNet;
-    let git_repo = "https://github.com/daoplays/sol_verify/archive/refs/heads/main.zip".to_string();
-    let git_commit = "".to_string();
-    let directory = "sol_verify-main/program".to_string();
-    let docker_version = "".to_string();
-    let rust_version = "1.62".to_string();
-    let solana_version = "1.10.39".to_string();
-    let anchor_version = "0.25.0".to_string();
+    emit(config.output, &HashReport {target : target.to_string(), hash : to_hex(&canonical_hash(program_bytes)), bytes : program_bytes.len()})
+}
 
+async fn diff_programs(config : &ClientConfig, expected : &str, candidate : &str) ->Result<()> {
 
+    let expected_data = load_program_target(config, expected).await?;
+    let candidate_data = load_program_target(config, candidate).await?;
 
-    let network_string = "dev_net".to_string();
+    let expected_bytes = canonical_program_bytes(&expected_data);
+    let candidate_bytes = canonical_program_bytes(&candidate_data);
 
-    let program_address = Pubkey::from_str(PROGRAM_KEY).unwrap();
+    let identical = expected_bytes == candidate_bytes;
+    let diff = if identical { None } else { ProgramDiff::new(expected_bytes, candidate_bytes) };
 
-    let (expected_metadata_key, _bump_seed) = Pubkey::find_program_address(&[&real_address.to_bytes(), &network_string.as_bytes()], &program_address);
+    emit(config.output, &DiffReport {
+        expected : expected.to_string(),
+        candidate : candidate.to_string(),
+        identical : identical,
+        summary : diff.as_ref().map(|diff| diff.summary().to_string()),
+        diff : diff
+    })
+}
 
-    let (expected_userdata_key, _bump_seed) = Pubkey::find_program_address(&[&wallet.pubkey().to_bytes(), b"user_account"], &program_address);
+async fn disasm_diff_programs(config : &ClientConfig, expected : &str, candidate : &str) ->Result<()> {
 
-    let meta_data =  SubmitProgramMeta{
-        address: real_address, 
-        network : network,
-        git_repo : git_repo, 
-        git_commit : git_commit, 
-        directory : directory, 
-        docker_version : docker_version,
-        rust_version : rust_version,
-        solana_version : solana_version,
-        anchor_version : anchor_version
-    };
+    let expected_data = load_program_target(config, expected).await?;
+    let candidate_data = load_program_target(config, candidate).await?;
 
+    let expected_functions = disasm::disassemble(canonical_program_bytes(&expected_data))?;
+    let candidate_functions = disasm::disassemble(canonical_program_bytes(&candidate_data))?;
 
-    let instruction = Instruction::new_with_borsh(
-        program_address,
-        &VerifyInstruction::SubmitProgram {metadata : meta_data},
-        vec![
-            AccountMeta::new_readonly(wallet.pubkey(), true),
-            AccountMeta::new(expected_metadata_key, false),
-            AccountMeta::new(expected_userdata_key, false),
-            AccountMeta::new_readonly(solana_sdk::system_program::id(), false)
-        ],
-    );
-
-    let signers = [&wallet];
-    let instructions = vec![instruction];
-    let recent_hash = client.get_latest_blockhash()?;
-
-    let txn = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&wallet.pubkey()),
-        &signers,
-        recent_hash,
-    );
-
-    let signature = client.send_and_confirm_transaction(&txn)?;
-    println!("signature: {}", signature);
-    let response = client.get_transaction(&signature, UiTransactionEncoding::Json)?;
-    println!("result: {:#?}", response); 
-
-    Ok(println!("Success!"))
+    emit(config.output, &DisassemblyDiff::new(&expected_functions, &candidate_functions))
 }
 
-fn get_program_client(network : Network) -> RpcClient {
+async fn show_toolchain(config : &ClientConfig, target : &str) ->Result<()> {
 
-    match network {
-        Network::TestNet => RpcClient::new(SOLANA_TEST),
-        Network::DevNet => RpcClient::new(SOLANA_DEV),
-        Network::MainNet => RpcClient::new(SOLANA_MAIN),
-        Network::Invalid => RpcClient::new(SOLANA_TEST),
-    }
-}
+    let data = load_program_target(config, target).await?;
+    let info = ToolchainInfo::extract(canonical_program_bytes(&data));
 
-struct upgrade_info {
-    slot : u64,
-    upgrade_authority : Option<Pubkey>
+    emit(config.output, &ToolchainReport {suggested_manifest : info.suggested_manifest(), toolchain : info})
 }
 
-fn verify_program(key_file: &String, test_key_file: &String, real_address_string: &String, network : Network, user_address : &String, git_repo: &String, git_commit: &String, directory: &String) ->Result<()> {
-
-    // (2) Create a new Keypair for the new account
-    let wallet = read_keypair_file(key_file).unwrap();
-    let test_keypair = read_keypair_file(test_key_file).unwrap();
-
-
-    // (3) Create RPC client to be used to talk to Solana cluster
-    let client = RpcClient::new(URL);
-
-    let program_client = get_program_client(network);
-/*
-   
-    let security_txt = solana_security_txt::find_and_parse(program_data).unwrap();
-    println!("{}", security_txt);
+async fn verify_local(config : &ClientConfig, address : Pubkey, so_path : &str) ->Result<()> {
 
-*/
+    let local_data = std::fs::read(so_path).map_err(|err| Error::FileReadError(so_path.to_string(), err))?;
+    let chain_data = fetch_program_elf(config.verifier_client().rpc(), &address).await?;
 
+    let local_elf = canonical_program_bytes(&local_data);
+    let chain_elf = canonical_program_bytes(&chain_data);
 
-    let real_address = Pubkey::from_str(real_address_string).unwrap();
-    let test_address = test_keypair.pubkey();
-    let program_address = Pubkey::from_str(PROGRAM_KEY).unwrap();
+    let local_hash = canonical_hash(local_elf);
+    let chain_hash = canonical_hash(chain_elf);
+    let matches = local_hash == chain_hash;
 
-    let real_program_account = program_client.get_account(&real_address)?;
-    let test_program_account = client.get_account(&test_address)?;
-
-    if !bpf_loader_upgradeable::check_id(&real_program_account.owner) {
-        println!("Only accounts owned by the bpf_loader_upgradeable program are supported at the moment");
-        return Ok(());
-    }
+    emit(config.output, &LocalVerifyReport {
+        program : address.to_string(),
+        so_path : so_path.to_string(),
+        chain_hash : to_hex(&chain_hash),
+        chain_bytes : chain_elf.len(),
+        local_hash : to_hex(&local_hash),
+        local_bytes : local_elf.len(),
+        matches : matches,
+        diff : if matches { None } else { ProgramDiff::new(chain_elf, local_elf) }
+    })
+}
 
-    if !bpf_loader_upgradeable::check_id(&test_program_account.owner) {
-        println!("Only accounts owned by the bpf_loader_upgradeable program are supported at the moment");
-        return Ok(());
-    }
+#[allow(clippy::too_many_arguments)]
+async fn verify_program(config : &ClientConfig, test_key_file: &str, real_address : Pubkey, network : Network, user_pubkey : Pubkey, git_repo: String, git_commit: String, directory: String, send : &SendArgs) ->Result<()> {
 
-    let real_program: UpgradeableLoaderState = bincode::deserialize_from(&real_program_account.data[..]).unwrap();
-    let test_program: UpgradeableLoaderState = bincode::deserialize_from(&test_program_account.data[..]).unwrap();
-
-    let real_program_data_address = if let UpgradeableLoaderState::Program {
-        programdata_address,
-    } = real_program
-    {
-        programdata_address
-    } else {
-        println!("Wrong program account type");
-        return Ok(());
-    };
+    let wallet = config.keypair()?;
+    let test_keypair = read_keypair_file(test_key_file)
+        .map_err(|err| Error::InvalidConfig(format!("failed to read keypair {}: {}", test_key_file, err)))?;
 
-    let test_program_data_address = if let UpgradeableLoaderState::Program {
-        programdata_address,
-    } = test_program
-    {
-        programdata_address
-    } else {
-        println!("Wrong program account type");
-        return Ok(());
+    let request = VerifyRequest {
+        real_address : real_address,
+        test_address : test_keypair.pubkey(),
+        network : network,
+        user : user_pubkey,
+        git_repo : git_repo,
+        git_commit : git_commit,
+        directory : directory
     };
 
-    let real_program_data_account = program_client.get_account(&real_program_data_address)?;
-    let test_program_data_account = client.get_account(&test_program_data_address)?;
-
-    let data_offset = UpgradeableLoaderState::programdata_data_offset().unwrap();
-    if real_program_data_account.data.len() < data_offset {
-        println!("Real account is too small to be a program data account");
-        return Ok(());
-    }
-
-    if test_program_data_account.data.len() < data_offset {
-        println!("Test account is too small to be a program data account");
-        return Ok(());
-    }
-
-    let real_program_data = &real_program_data_account.data[data_offset..];
-    let test_program_data = &test_program_data_account.data[data_offset..];
-
-
-    let (verified, test_hash) = get_sha256_hashed_data(real_program_data, test_program_data);
-
-    println!("verified {}", verified);
-
-
-    let real_meta : UpgradeableLoaderState = bincode::deserialize_from(&real_program_data_account.data[..data_offset]).unwrap();
-
-    let mut upgrade_info = upgrade_info {slot : 0, upgrade_authority : Some(system_program::ID)};
-
-    println!("data_buffer {:?}", real_meta);
+    let client = config.sending_client(send)?;
+    let report = client.verify(&wallet, &request).await?;
 
-    let mut upgradeable : bool = false;
-    match real_meta {
-        UpgradeableLoaderState::ProgramData{slot, upgrade_authority_address} => 
-        upgrade_info = upgrade_info {slot : slot, upgrade_authority  : upgrade_authority_address},
-        _ => println!("Account not upgradeable"),
+    if send.dry_run {
+        emit(config.output, &DryRunReport {command : "verify".to_string(), simulations : client.take_simulations()})?;
     }
-
-    
-    if upgrade_info.upgrade_authority.is_some() {
-        upgradeable = true;
-    }
-
-    let mut verified_code : u8 = 1;
-    if !verified {
-        verified_code = 1;
+    else {
+        emit(config.output, &report)?;
     }
 
-    if verified && upgradeable {
-        verified_code  = 2;
+    // the user has been told, but a build of the wrong commit is still a failed verification
+    if report.status_code == state::SOURCE_REVISION_MISMATCH {
+        std::process::exit(1);
     }
+    Ok(())
+}
 
-    if verified && !upgradeable {
-        verified_code  = 3;
-    }
+async fn check_metadata(config : &ClientConfig, real_address : Pubkey, network : Network) ->Result<()> {
 
-    let current_slot = program_client.get_slot()?;
+    let report = config.verifier_client().program_record(real_address, network).await?;
 
-    let network_string = network_to_string(network);
-    let (expected_metadata_key, _bump_seed) = Pubkey::find_program_address(&[&real_address.to_bytes(), &network_string.as_bytes()], &program_address);
+    emit(config.output, &report)
+}
 
-    // check the current meta data
-    let response = client.get_account_data(&expected_metadata_key)?;
-    let current_state = ProgramMetaData::try_from_slice(&response[..]).unwrap();
+async fn check_status(config : &ClientConfig, user_pubkey : Pubkey) ->Result<()> {
 
-    let mut message = "Program ".to_owned();
-    message += real_address_string;
-    message += " : ";
+    let report = config.verifier_client().user_status(user_pubkey).await?;
 
-    // if the program has already had a verification run, and it has code = 3 (passed, immutable) then we only want to update
-    // the state if it still passes
+    emit(config.output, &report)
+}
 
-    if current_state.verified_code == 3 && !verified {
-        message += "program already verified and immutable but new verification fails.  Not updating state.";
-        update_status(key_file, user_address, 109  as u8, &message)?;
-        return Ok(println!("{}", message))
-    }
+async fn update_status(config : &ClientConfig, user_pubkey : Pubkey, status_code : u8, stage : VerificationStage, progress : Option<u8>, log_message : &str, send : &SendArgs) ->Result<()> {
 
-    // if a previous verification was code = 2 (passed, mutable), then we need to check if the last update time was after the last verification time.  If it wasn't then we only update if the verification still passes.
+    let wallet = config.keypair()?;
+    let client = config.sending_client(send)?;
+    let report = client.send_status(&wallet, user_pubkey, status_code, stage, progress, log_message).await?;
 
-    if current_state.verified_code == 2 && upgrade_info.slot < current_state.last_verified_slot && !verified {
-        message +=  "program already verified and not updated since last verification but new verification fails.  Not updating state.";
-        update_status(key_file, user_address, 110  as u8, &message)?;
-        return Ok(println!("{}", message))
+    if send.dry_run {
+        return emit(config.output, &DryRunReport {command : "update-status".to_string(), simulations : client.take_simulations()});
     }
+    emit(config.output, &report)
+}
 
-    // if the update time is after the last verification time then we will always perform the verification again.
-    // Similarly if code == 1 or 0 (failed, or this is the first time) then we will always perform it again.
-
-    let meta_data =  VerifyProgramMeta{verified_code: verified_code, real_address : real_address, test_address : test_address, data_hash : test_hash, verified_slot : current_slot, network : network, git_repo : git_repo.to_string(), git_commit: git_commit.to_string(), directory : directory.to_string() };
-
-    let instruction = Instruction::new_with_borsh(
-        program_address,
-        &VerifyInstruction::VerifyProgram {metadata : meta_data},
-        vec![
-            AccountMeta::new_readonly(wallet.pubkey(), true),
-            AccountMeta::new(expected_metadata_key, false),
-
-            AccountMeta::new_readonly(real_address, false),
-            AccountMeta::new_readonly(test_address, false)
-        ],
-    );
-
-    let signers = [&wallet];
-    let instructions = vec![instruction];
-    let recent_hash = client.get_latest_blockhash()?;
-
-    let txn = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&wallet.pubkey()),
-        &signers,
-        recent_hash,
-    );
-
-    let signature = client.send_and_confirm_transaction(&txn)?;
-    println!("signature: {}", signature);
-    let response = client.get_transaction(&signature, UiTransactionEncoding::Json)?;
-    println!("result: {:#?}", response); 
-
- 
-
-    // finally update the user with the result
-    if verified_code == 1 {
-        message += "Verification process has not produced a match";
-        update_status(key_file, user_address, 1  as u8, &message)?;
-        return Ok(println!("{}", message))
-    }
+async fn get_security(config : &ClientConfig, target : &str, json_file : Option<&str>) ->Result<()> {
 
-    if verified_code == 2 {
-        message +=  "Verification was successful, however the program is upgradable";
-        update_status(key_file, user_address, 1  as u8, &message)?;
-        return Ok(println!("{}", message))
+    // a local file hasn't been deployed, so can't have an upgrade authority
+    let report = if std::path::Path::new(target).exists() {
+        let program_data = load_program_target(config, target).await?;
+        SecurityReport::new(target.to_string(), false, solana_security_txt::find_and_parse(&program_data))
     }
+    else {
+        let address = Pubkey::from_str(target)
+            .map_err(|_| Error::InvalidConfig(format!("{} is neither a file nor a program address", target)))?;
+        config.verifier_client().security_report(address).await?
+    };
 
-    if verified_code == 3 {
-        message +=  "Verification was successful, and program is immutable";
-        update_status(key_file, user_address, 1  as u8, &message)?;
-        return Ok(println!("{}", message))
+    if let Some(json_file) = json_file {
+        let mut file = File::create(json_file).map_err(|err| Error::FileReadError(json_file.to_string(), err))?;
+        file.write_all(serde_json::to_string_pretty(&report)?.as_bytes()).map_err(|err| Error::FileReadError(json_file.to_string(), err))?;
     }
 
-    Ok(println!("Success!"))
+    emit(config.output, &report)
 }
 
+async fn pin_program_source(config : &ClientConfig, address : Pubkey, git_repo : String, git_commit : String, directory : String) ->Result<()> {
 
-fn check_metadata() ->Result<()> {
-
- 
-    // (3) Create RPC client to be used to talk to Solana cluster
-    let client = RpcClient::new(URL);
+    let program_data = fetch_program_elf(config.verifier_client().rpc(), &address).await?;
 
+    let pinned = match solana_security_txt::find_and_parse(&program_data) {
+        Ok(security_txt) => pin_source(&git_repo, &git_commit, &directory, &security_txt).await?,
+        Err(_) => PinnedSource {git_repo : git_repo, git_commit : git_commit, directory : directory, declared : None}
+    };
 
-    let real_address = Pubkey::from_str("7EGMFCt38NyXZHsR7G3JeBgMkNPhGF3z8g1pVLEXPA8Y").unwrap();
-    let program_address = Pubkey::from_str(PROGRAM_KEY).unwrap();
-
-    let network_string : String = "dev_net".to_string();
-    let (expected_metadata_key, _bump_seed) = Pubkey::find_program_address(&[&real_address.to_bytes(), &network_string.as_bytes()], &program_address);
-
-    let response = client.get_account_data(&expected_metadata_key)?;
-    println!("data in account: {}", expected_metadata_key);
-
-    let current_state = ProgramMetaData::try_from_slice(&response[..]).unwrap();
-
-    println!("verified: {}", current_state.verified_code);   
-    println!("last_verified_slot: {}", current_state.last_verified_slot);    
-    println!("test_address: {}", current_state.test_address); 
-    println!("data: {:?}", current_state.data_hash);    
- 
-
-    Ok(println!("Success!"))
+    emit(config.output, &pinned)
 }
 
+async fn scan_registry(config : &ClientConfig, accounts : ScanAccounts, format : ExportFormat, out : Option<&str>, check_staleness : bool) ->Result<()> {
 
-fn update_status(key_file : &String, user_address : &String, status_code : u8, log_message : &String) ->Result<()> {
-
-    // (2) Create a new Keypair for the new account
-    let wallet = read_keypair_file(key_file).unwrap();
-
-    // (3) Create RPC client to be used to talk to Solana cluster
-    let client = RpcClient::new(URL);
-
-    let program_address = Pubkey::from_str(PROGRAM_KEY).unwrap();
-    let user_pubkey = Pubkey::from_str(user_address).unwrap();
-
-
-    let (expected_userdata_key, _bump_seed) = Pubkey::find_program_address(&[&user_pubkey.to_bytes(), b"user_account"], &program_address);
-
-    let meta_data =  StatusMeta{user_pubkey : user_pubkey, status_code : status_code, log_message : log_message.to_string()};
-
-
-    let instruction = Instruction::new_with_borsh(
-        program_address,
-        &VerifyInstruction::UpdateStatus {metadata : meta_data},
-        vec![
-            AccountMeta::new_readonly(wallet.pubkey(), true),
-            AccountMeta::new(expected_userdata_key, false),
-            AccountMeta::new_readonly(solana_sdk::system_program::id(), false)
-        ],
-    );
-
-    let signers = [&wallet];
-    let instructions = vec![instruction];
-    let recent_hash = client.get_latest_blockhash()?;
-
-    let txn = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&wallet.pubkey()),
-        &signers,
-        recent_hash,
-    );
-
-    let signature = client.send_transaction(&txn)?;
-    println!("signature: {}", signature);
-    
+    let client = config.verifier_client();
+    let report = match accounts {
+        ScanAccounts::Records => ExportReport::new("records", &client.scan_records(check_staleness).await?, format, out)?,
+        ScanAccounts::Users => ExportReport::new("users", &client.scan_users().await?, format, out)?
+    };
 
-    Ok(println!("Success!"))
+    emit_export(config, &report)
 }
 
+// the export is written before anything is printed, so a failed write is reported as the command's error
+fn emit_export(config : &ClientConfig, report : &ExportReport) ->Result<()> {
 
-fn get_security(program_address_string : &String) ->Result<()> {
-
-    let client = RpcClient::new(URL);
-
-    let program_address = Pubkey::from_str(program_address_string).unwrap();
-
-    let program_account = client.get_account(&program_address)?;
-
-    if !bpf_loader_upgradeable::check_id(&program_account.owner) {
-        println!("wrong owner");
-        return Ok(());
+    if let Some(out) = &report.out {
+        write_export(&report.export, out)?;
     }
 
-    let program: UpgradeableLoaderState = bincode::deserialize_from(&program_account.data[..]).unwrap();
+    emit(config.output, report)
+}
 
-    let program_data_address = if let UpgradeableLoaderState::Program {
-        programdata_address,
-    } = program
-    {
-        programdata_address
-    } else {
-        return Ok(());
-    };
+// writes an export or a badge to a file
+fn write_export(export : &str, out : &str) ->Result<()> {
 
-    let program_data_account = client.get_account(&program_data_address)?;
+    let mut file = File::create(out).map_err(|err| Error::FileReadError(out.to_string(), err))?;
+    file.write_all(export.as_bytes()).map_err(|err| Error::FileReadError(out.to_string(), err))
+}
 
-    let offset = UpgradeableLoaderState::programdata_data_offset().unwrap();
-    if program_data_account.data.len() < offset {
-        return Ok(());
-    }
+async fn sync_index(config : &ClientConfig, db : &str) ->Result<()> {
+
+    let client = config.verifier_client();
+    let mut index = VerificationIndex::open(db, &client.program_id())?;
 
-    let real_meta : UpgradeableLoaderState = bincode::deserialize_from(&program_data_account.data[..offset]).unwrap();
+    let report = index.sync(&client).await?;
 
-    let mut upgrade_info = upgrade_info {slot : 0, upgrade_authority : Some(system_program::ID)};
+    emit(config.output, &report)
+}
 
-    println!("data_buffer {:?}", real_meta);
+fn query_index(config : &ClientConfig, db : &str, query : RecordQuery, format : ExportFormat, out : Option<&str>) ->Result<()> {
 
-    let mut upgradeable : bool = false;
-    match real_meta {
-        UpgradeableLoaderState::ProgramData{slot, upgrade_authority_address} => 
-        upgrade_info = upgrade_info {slot : slot, upgrade_authority  : upgrade_authority_address},
-        _ => println!("Account not upgradeable"),
-    }
+    let index = VerificationIndex::open(db, &config.program_id)?;
 
-    if upgrade_info.upgrade_authority.is_some() {
-        upgradeable = true;
-    }
+    emit_export(config, &ExportReport::new("records", &index.records(&query)?, format, out)?)
+}
 
-    let program_data = &program_data_account.data[offset..];
+async fn show_history(config : &ClientConfig, program : Pubkey, network : Network) ->Result<()> {
 
-    let security_txt = solana_security_txt::find_and_parse(program_data).unwrap();
-    println!("{}", security_txt);
+    let history = config.verifier_client().program_history(&program, network).await?;
 
-    let mut json_data = ProgramJsonData {
-        upgradeable: upgradeable,
-        source_code: "none".to_owned(),
-    };
+    emit(config.output, &history)
+}
 
+async fn decode_transaction(config : &ClientConfig, signature : &Signature) ->Result<()> {
 
-    if security_txt.source_code.is_some() {
-        json_data.source_code = security_txt.source_code.unwrap();
-    }
+    let decoded = config.verifier_client().decode_transaction(signature).await?;
 
-    let j = serde_json::to_string(&json_data).unwrap();
+    emit(config.output, &decoded)
+}
 
-    let mut file = File::create("program_meta_data.json").unwrap();
-    file.write_all(j.as_bytes()).unwrap();
+async fn write_badge(config : &ClientConfig, program : Pubkey, network : Network, format : BadgeFormat, out : &str) ->Result<()> {
 
+    let badge = config.verifier_client().badge(&program, network).await?;
 
-    Ok(())
+    write_export(&badge.render(format)?, out)?;
 
+    emit(config.output, &BadgeReport {program : program.to_string(), network : network_to_string(network), message : badge.message(), badge : badge, out : out.to_string()})
 }
@@ -0,0 +1,228 @@
+use crate::state::{Error, Result, Network, SubmitProgramMeta, CODE_META_SIZE, GIT_COMMIT_BEGIN, GIT_DIR_BEGIN, GIT_DIR_END};
+
+use serde::{Deserialize, Serialize};
+use solana_program::pubkey::Pubkey;
+use std::path::Path;
+use std::str::FromStr;
+
+/// manifest file looked for in the current directory when --manifest isn't given
+pub const DEFAULT_MANIFEST : &str = "sol_verify.json";
+
+/// docker images the verifier has prebuilt, any other build has to give its toolchain versions
+pub const KNOWN_DOCKER_VERSIONS : [&str; 1] = ["solana_v1.10.39"];
+
+// the program stores repo, commit and directory together with these markers in code_meta
+const CODE_META_MARKERS : [&str; 3] = [GIT_COMMIT_BEGIN, GIT_DIR_BEGIN, GIT_DIR_END];
+
+/// Everything needed to submit a program.  Fields can come from a manifest file, the command line, or both,
+/// with the command line taking precedence
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubmitManifest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_repo : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_commit : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docker_version : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rust_version : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub solana_version : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor_version : Option<String>
+}
+
+impl SubmitManifest {
+
+    pub fn load(path : &Path) -> Result<SubmitManifest> {
+        let file = std::fs::File::open(path).map_err(Error::ConfigReadError)?;
+
+        serde_json::from_reader(file)
+            .map_err(|err| Error::InvalidSubmission(format!("failed to parse manifest {}: {}", path.display(), err)))
+    }
+
+    /// fill in anything not already set from other, so that self wins
+    pub fn or(self, other : SubmitManifest) -> SubmitManifest {
+        SubmitManifest {
+            address : self.address.or(other.address),
+            network : self.network.or(other.network),
+            git_repo : self.git_repo.or(other.git_repo),
+            git_commit : self.git_commit.or(other.git_commit),
+            directory : self.directory.or(other.directory),
+            docker_version : self.docker_version.or(other.docker_version),
+            rust_version : self.rust_version.or(other.rust_version),
+            solana_version : self.solana_version.or(other.solana_version),
+            anchor_version : self.anchor_version.or(other.anchor_version)
+        }
+    }
+
+    /// check every field and build the instruction data, reporting all the problems found rather than just the first
+    pub fn validate(self) -> Result<SubmitProgramMeta> {
+
+        let mut problems : Vec<String> = Vec::new();
+
+        let address = match self.address.as_deref() {
+            None => { problems.push("program address is required".to_string()); None },
+            Some(address) => match Pubkey::from_str(address) {
+                Ok(pubkey) => Some(pubkey),
+                Err(_) => { problems.push(format!("program address {} is not a valid public key", address)); None }
+            }
+        };
+
+        let network = match Network::from_str(self.network.as_deref().unwrap_or("dev_net")) {
+            Ok(network) => network,
+            Err(err) => { problems.push(err.to_string()); Network::Invalid }
+        };
+
+        let git_repo = self.git_repo.unwrap_or_default();
+        if git_repo.is_empty() {
+            problems.push("git repo is required".to_string());
+        }
+        else if !git_repo.starts_with("https://") && !git_repo.starts_with("http://") {
+            problems.push(format!("git repo {} must be an http(s) url", git_repo));
+        }
+
+        // without a commit the repo is fetched as an archive, with one it is cloned and checked out
+        let git_commit = self.git_commit.unwrap_or_default();
+        if !git_commit.is_empty() && !is_commit_hash(&git_commit) {
+            problems.push(format!("git commit {} must be a 7 to 40 character hex hash", git_commit));
+        }
+
+        let directory = self.directory.unwrap_or_default();
+        if directory.starts_with('/') || directory.split('/').any(|part| part == "..") {
+            problems.push(format!("directory {} must be relative to the repo root", directory));
+        }
+
+        let code_meta_len = git_repo.len() + git_commit.len() + directory.len() + CODE_META_MARKERS.iter().map(|m| m.len()).sum::<usize>();
+        if code_meta_len > CODE_META_SIZE {
+            problems.push(format!("git repo, commit and directory take {} bytes, the program can store at most {}", code_meta_len, CODE_META_SIZE));
+        }
+
+        let docker_version = self.docker_version.unwrap_or_default();
+        let rust_version = self.rust_version.unwrap_or_default();
+        let solana_version = self.solana_version.unwrap_or_default();
+        let anchor_version = self.anchor_version.unwrap_or_default();
+
+        if !docker_version.is_empty() {
+            if !KNOWN_DOCKER_VERSIONS.contains(&docker_version.as_str()) {
+                problems.push(format!("unknown docker version {}, expected one of {:?}", docker_version, KNOWN_DOCKER_VERSIONS));
+            }
+        }
+        else {
+            if rust_version.is_empty() || solana_version.is_empty() {
+                problems.push("rust and solana versions are required when no docker version is given".to_string());
+            }
+        }
+
+        for (name, version) in [("rust", &rust_version), ("solana", &solana_version), ("anchor", &anchor_version)] {
+            if !version.is_empty() && !is_version(version) {
+                problems.push(format!("{} version {} should look like 1.2 or 1.2.3", name, version));
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(Error::InvalidSubmission(problems.join("; ")));
+        }
+
+        Ok(SubmitProgramMeta {
+            address : address.unwrap(),
+            network : network,
+            git_repo : git_repo,
+            git_commit : git_commit,
+            directory : directory,
+            docker_version : docker_version,
+            rust_version : rust_version,
+            solana_version : solana_version,
+            anchor_version : anchor_version
+        })
+    }
+}
+
+pub fn is_commit_hash(commit : &str) -> bool {
+    (7..=40).contains(&commit.len()) && commit.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_version(version : &str) -> bool {
+    let parts : Vec<&str> = version.split('.').collect();
+    (2..=3).contains(&parts.len()) && parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS : &str = "7LtYL85tZPpYweZMqeHzX6DAaGsrY61DEtnwiPyJaVCD";
+
+    fn manifest() -> SubmitManifest {
+        SubmitManifest {
+            address : Some(ADDRESS.to_string()),
+            git_repo : Some("https://github.com/daoplays/sol_verify".to_string()),
+            directory : Some("program".to_string()),
+            docker_version : Some(KNOWN_DOCKER_VERSIONS[0].to_string()),
+            ..SubmitManifest::default()
+        }
+    }
+
+    fn problems(manifest : SubmitManifest) -> String {
+        match manifest.validate() {
+            Err(Error::InvalidSubmission(problems)) => problems,
+            other => panic!("expected an invalid submission, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn a_complete_manifest_is_valid() {
+        let meta = manifest().validate().unwrap();
+        assert_eq!(meta.address.to_string(), ADDRESS);
+        assert_eq!(meta.network, Network::DevNet);
+        assert_eq!(meta.git_commit, "");
+
+        let versions = SubmitManifest {
+            network : Some("mainnet-beta".to_string()),
+            git_commit : Some("57d41cf".to_string()),
+            docker_version : None,
+            rust_version : Some("1.62".to_string()),
+            solana_version : Some("1.10.39".to_string()),
+            ..manifest()
+        };
+        assert_eq!(versions.validate().unwrap().network, Network::MainNet);
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let problems = problems(SubmitManifest {
+            address : Some("not-a-key".to_string()),
+            git_repo : Some("git@github.com:daoplays/sol_verify.git".to_string()),
+            git_commit : Some("main".to_string()),
+            directory : Some("../program".to_string()),
+            ..manifest()
+        });
+        assert_eq!(problems.split("; ").count(), 4, "{}", problems);
+    }
+
+    #[test]
+    fn required_fields_are_checked() {
+        assert!(problems(SubmitManifest {address : None, ..manifest()}).contains("program address is required"));
+        assert!(problems(SubmitManifest {git_repo : None, ..manifest()}).contains("git repo is required"));
+        assert!(problems(SubmitManifest {network : Some("localnet".to_string()), ..manifest()}).contains("unknown network"));
+        assert!(problems(SubmitManifest {docker_version : None, ..manifest()}).contains("rust and solana versions are required"));
+        assert!(problems(SubmitManifest {docker_version : Some("latest".to_string()), ..manifest()}).contains("unknown docker version"));
+    }
+
+    #[test]
+    fn values_are_checked() {
+        assert!(problems(SubmitManifest {directory : Some("/program".to_string()), ..manifest()}).contains("relative"));
+        assert!(problems(SubmitManifest {git_commit : Some("57d41c".to_string()), ..manifest()}).contains("hex hash"));
+        assert!(problems(SubmitManifest {anchor_version : Some("0.25.x".to_string()), ..manifest()}).contains("anchor version"));
+
+        let long_directory = "a".repeat(CODE_META_SIZE);
+        assert!(problems(SubmitManifest {directory : Some(long_directory), ..manifest()}).contains("at most 512"));
+    }
+}
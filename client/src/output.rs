@@ -0,0 +1,512 @@
+//! What each command prints.  With --output json a command prints exactly one of these objects, and on failure
+//! prints an ErrorReport instead, so scripts can read the result without parsing text.  Addresses and signatures
+//! are base58 strings, hashes are lowercase hex and networks are test_net, dev_net or main_net
+
+use crate::state::{Result, StatusEntry, VerificationStage, CodeMeta};
+use crate::diff::ProgramDiff;
+use crate::disasm::DisassemblyDiff;
+use crate::manifest::SubmitManifest;
+use crate::security::SecurityReport;
+use crate::toolchain::ToolchainInfo;
+use crate::index::SyncReport;
+use crate::badge::Badge;
+use crate::decode::DecodedTransaction;
+use crate::history::ProgramHistory;
+use crate::simulate::Simulation;
+use crate::revision::PinnedSource;
+use crate::registry::{self, CsvRow, ExportFormat};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json
+}
+
+/// Something a command reports, printable either as text or as a single json object
+pub trait Report : Serialize {
+    fn print(&self);
+}
+
+pub fn emit<R : Report>(format : OutputFormat, report : &R) -> Result<()> {
+    match format {
+        OutputFormat::Text => report.print(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(report)?)
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ErrorReport {
+    pub error : String,
+    // 2 for a bad configuration, 1 for anything else
+    pub exit_code : i32,
+    // the status code the verifier sends the user for this error, None if it isn't one the user is told about
+    pub status_code : Option<u8>
+}
+
+impl Report for ErrorReport {
+    fn print(&self) {
+        eprintln!("error: {}", self.error);
+    }
+}
+
+/// The result of submit
+#[derive(Serialize, Debug, Clone)]
+pub struct SubmitReport {
+    pub program : String,
+    pub network : String,
+    pub git_repo : String,
+    pub git_commit : String,
+    pub directory : String,
+    // the source_revision or source_release from security.txt that the commit was pinned to
+    pub declared_revision : Option<String>,
+    // problems that didn't stop the submission, such as git not being available to pin the commit
+    pub warnings : Vec<String>,
+    // the program derived accounts holding the program's record and the user's status
+    pub metadata_account : String,
+    pub user_account : String,
+    pub signature : String
+}
+
+impl Report for SubmitReport {
+    fn print(&self) {
+        for warning in self.warnings.iter() {
+            println!("warning: {}", warning);
+        }
+        if let Some(declared) = &self.declared_revision {
+            println!("using commit {} of {} from security.txt revision {}", self.git_commit, self.git_repo, declared);
+        }
+        println!("metadata account: {}", self.metadata_account);
+        println!("user account: {}", self.user_account);
+        println!("signature: {}", self.signature);
+        println!("submitted {} on {} for verification", self.program, self.network);
+    }
+}
+
+/// The result of verify, as recorded on chain and sent to the user
+#[derive(Serialize, Debug, Clone)]
+pub struct VerifyReport {
+    pub program : String,
+    pub test_program : String,
+    pub network : String,
+    pub user : String,
+    pub metadata_account : String,
+    // canonical hashes of the deployed program and of the test build
+    pub expected_hash : String,
+    pub candidate_hash : String,
+    pub verified : bool,
+    pub upgradeable : bool,
+    // slot the deployed program was last upgraded in
+    pub last_upgrade_slot : u64,
+    // the verified code recorded for the program, None if the record was left unchanged
+    pub verified_code : Option<u8>,
+    // the status code and message sent to the user
+    pub status_code : u8,
+    pub message : String,
+    // signature of the transaction recording the result, None if nothing was recorded
+    pub signature : Option<String>,
+    // how the two builds differ, when they do
+    pub diff : Option<ProgramDiff>
+}
+
+impl Report for VerifyReport {
+    fn print(&self) {
+        println!("real: {}", self.expected_hash);
+        println!("test: {}", self.candidate_hash);
+        println!("verified {}", self.verified);
+        if !self.verified {
+            print_diff(&self.diff);
+        }
+        println!("upgradeable: {}", self.upgradeable);
+        if let Some(signature) = &self.signature {
+            println!("signature: {}", signature);
+        }
+        println!("{}", self.message);
+    }
+}
+
+/// The verification record for a program, from show
+#[derive(Serialize, Debug, Clone)]
+pub struct ProgramRecordReport {
+    pub program : String,
+    pub network : String,
+    pub metadata_account : String,
+    pub verified_code : u8,
+    pub description : String,
+    pub last_verified_slot : u64,
+    pub test_address : String,
+    // canonical hash of the build that was verified
+    pub data_hash : String,
+    // the repo, commit and directory the program was verified from, None if none has been recorded
+    pub source : Option<CodeMeta>,
+    // None for records written before the bump seed was stored
+    pub bump : Option<u8>,
+    // how the program is deployed now
+    pub upgradeable : bool,
+    pub upgrade_authority : Option<String>,
+    pub last_deploy_slot : Option<u64>,
+    // true if the program has been upgraded since it was verified, None if it never has been
+    pub stale : Option<bool>
+}
+
+impl Report for ProgramRecordReport {
+    fn print(&self) {
+        println!("program: {} on {}", self.program, self.network);
+        match self.bump {
+            Some(bump) => println!("record account: {} (bump {})", self.metadata_account, bump),
+            None => println!("record account: {} (legacy record without a bump seed)", self.metadata_account)
+        }
+        println!("verified code: {} ({})", self.verified_code, self.description);
+        if let Some(source) = &self.source {
+            println!("git repo: {}", source.git_repo);
+            println!("git commit: {}", if source.git_commit.is_empty() { "none, fetched as an archive" } else { &source.git_commit });
+            println!("directory: {}", if source.directory.is_empty() { "." } else { &source.directory });
+        }
+        println!("verified hash: {}", self.data_hash);
+        println!("test build: {}", self.test_address);
+        println!("last verified slot: {}", self.last_verified_slot);
+        match &self.upgrade_authority {
+            Some(authority) => println!("upgrade authority: {}", authority),
+            None => println!("upgrade authority: none, the program is immutable")
+        }
+        if let Some(slot) = self.last_deploy_slot {
+            println!("last deployed slot: {}", slot);
+        }
+        match self.stale {
+            Some(true) => println!("stale: the program was upgraded after it was verified"),
+            Some(false) => println!("stale: no"),
+            None => {}
+        }
+    }
+}
+
+/// A user's current status and status history, from status
+#[derive(Serialize, Debug, Clone)]
+pub struct StatusReport {
+    pub user : String,
+    pub user_account : String,
+    pub status_code : u8,
+    pub description : String,
+    pub stage : VerificationStage,
+    pub progress : Option<u8>,
+    pub message : Option<String>,
+    // oldest first, timestamps are unix seconds
+    pub history : Vec<StatusEntry>
+}
+
+impl Report for StatusReport {
+    fn print(&self) {
+        println!("data in account: {}", self.user_account);
+        println!("status_code: {}", self.status_code);
+        println!("description: {}", self.description);
+        println!("stage: {}", self.stage.name());
+        if let Some(progress) = self.progress {
+            println!("progress: {}%", progress);
+        }
+        if let Some(message) = &self.message {
+            println!("message: {}", message);
+        }
+        println!("history:");
+        for entry in self.history.iter() {
+            println!("  {} code {} stage {} : {}", entry.timestamp, entry.status_code, entry.stage.name(), entry.log_message);
+        }
+    }
+}
+
+/// The status sent by update-status
+#[derive(Serialize, Debug, Clone)]
+pub struct StatusUpdateReport {
+    pub user : String,
+    pub user_account : String,
+    pub status_code : u8,
+    pub description : String,
+    pub stage : VerificationStage,
+    pub progress : Option<u8>,
+    pub message : String,
+    pub signature : String
+}
+
+impl Report for StatusUpdateReport {
+    fn print(&self) {
+        println!("signature: {}", self.signature);
+    }
+}
+
+/// The canonical hash of a program, from hash
+#[derive(Serialize, Debug, Clone)]
+pub struct HashReport {
+    pub target : String,
+    pub hash : String,
+    // length of the ELF the hash covers
+    pub bytes : usize
+}
+
+impl Report for HashReport {
+    fn print(&self) {
+        println!("{}  {} ({} bytes)", self.hash, self.target, self.bytes);
+    }
+}
+
+/// A comparison of a local build with a deployed program, from verify-local
+#[derive(Serialize, Debug, Clone)]
+pub struct LocalVerifyReport {
+    pub program : String,
+    pub so_path : String,
+    pub chain_hash : String,
+    pub chain_bytes : usize,
+    pub local_hash : String,
+    pub local_bytes : usize,
+    pub matches : bool,
+    pub diff : Option<ProgramDiff>
+}
+
+impl Report for LocalVerifyReport {
+    fn print(&self) {
+        println!("on chain: {} bytes, sha256 {}", self.chain_bytes, self.chain_hash);
+        println!("local:    {} bytes, sha256 {}", self.local_bytes, self.local_hash);
+        if self.matches {
+            println!("match: {} was built from {}", self.program, self.so_path);
+        }
+        else {
+            println!("mismatch: {} differs from {}", self.program, self.so_path);
+            print_diff(&self.diff);
+        }
+    }
+}
+
+/// The section and symbol differences between two programs, from diff
+#[derive(Serialize, Debug, Clone)]
+pub struct DiffReport {
+    pub expected : String,
+    pub candidate : String,
+    pub identical : bool,
+    // None when the programs are identical, or when one of them isn't an ELF
+    pub diff : Option<ProgramDiff>,
+    pub summary : Option<String>
+}
+
+impl Report for DiffReport {
+    fn print(&self) {
+        if self.identical {
+            println!("{} and {} are identical", self.expected, self.candidate);
+        }
+        else {
+            print_diff(&self.diff);
+        }
+    }
+}
+
+/// prints a section diff, or why there isn't one
+pub fn print_diff(diff : &Option<ProgramDiff>) {
+    match diff {
+        Some(diff) => {
+            diff.print();
+            if diff.differing_sections().any(|s| s.name == ".text") {
+                println!("use disasm-diff to compare the code instruction by instruction");
+            }
+        },
+        None => println!("can't compare sections as one of the programs is not an ELF")
+    }
+}
+
+impl Report for DisassemblyDiff {
+    fn print(&self) {
+        DisassemblyDiff::print(self)
+    }
+}
+
+/// The toolchain a program was built with, from toolchain
+#[derive(Serialize, Debug, Clone)]
+pub struct ToolchainReport {
+    pub toolchain : ToolchainInfo,
+    pub suggested_manifest : SubmitManifest
+}
+
+impl Report for ToolchainReport {
+    fn print(&self) {
+        self.toolchain.print();
+        println!("suggested manifest values:");
+        println!("{}", serde_json::to_string_pretty(&self.suggested_manifest).unwrap_or_default());
+        println!("rust_version can't be read from the program, as rustc above is the one bundled with the build tools");
+    }
+}
+
+impl Report for SecurityReport {
+    fn print(&self) {
+        SecurityReport::print(self)
+    }
+}
+
+impl Report for PinnedSource {
+    fn print(&self) {
+        match &self.declared {
+            Some(declared) => println!("security.txt declares {}, pinned to commit {}", declared, self.git_commit),
+            None => println!("security.txt doesn't declare a source revision, so the source is unchanged")
+        }
+        println!("git repo: {}", self.git_repo);
+        println!("git commit: {}", self.git_commit);
+        println!("directory: {}", self.directory);
+    }
+}
+
+impl Report for SyncReport {
+    fn print(&self) {
+        println!("replayed {} transactions: {} submissions, {} verifications, {} status updates",
+            self.signatures, self.submissions, self.verifications, self.status_updates);
+        println!("refreshed {} records and {} user accounts", self.records_refreshed, self.users_refreshed);
+        for instruction in self.undecoded_instructions.iter() {
+            println!("skipped undecodable instruction {}", instruction);
+        }
+        for record in self.unreadable_records.iter() {
+            println!("couldn't read record {}, left for the next sync", record);
+        }
+        for user in self.unreadable_users.iter() {
+            println!("couldn't read user account {}, left for the next sync", user);
+        }
+        if let Some(signature) = &self.last_signature {
+            println!("synced to {}", signature);
+        }
+    }
+}
+
+impl Report for DecodedTransaction {
+    fn print(&self) {
+        DecodedTransaction::print(self)
+    }
+}
+
+impl Report for ProgramHistory {
+    fn print(&self) {
+        ProgramHistory::print(self)
+    }
+}
+
+/// The badge written by badge
+#[derive(Serialize, Debug, Clone)]
+pub struct BadgeReport {
+    pub program : String,
+    pub network : String,
+    pub badge : Badge,
+    // the text shown on the badge
+    pub message : String,
+    pub out : String
+}
+
+impl Report for BadgeReport {
+    fn print(&self) {
+        println!("{} on {}: {}", self.program, self.network, self.message);
+        println!("badge written to {}", self.out);
+    }
+}
+
+/// The result of scan or index records.  As text this is the export itself, unless it was written to a file
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportReport {
+    // records or users
+    pub accounts : String,
+    pub count : usize,
+    // jsonl or csv
+    pub format : String,
+    // the file the export was written to, None if it was printed
+    pub out : Option<String>,
+    // every account exported, left out when they were written to a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries : Option<Vec<serde_json::Value>>,
+    #[serde(skip)]
+    pub export : String
+}
+
+impl ExportReport {
+    pub fn new<T : Serialize + CsvRow>(accounts : &str, entries : &[T], format : ExportFormat, out : Option<&str>) -> Result<ExportReport> {
+
+        let values = match out {
+            Some(_) => None,
+            None => Some(entries.iter().map(serde_json::to_value).collect::<serde_json::Result<Vec<serde_json::Value>>>()?)
+        };
+
+        Ok(ExportReport {
+            accounts : accounts.to_string(),
+            count : entries.len(),
+            format : match format {ExportFormat::Jsonl => "jsonl", ExportFormat::Csv => "csv"}.to_string(),
+            out : out.map(|out| out.to_string()),
+            entries : values,
+            export : registry::export(entries, format)?
+        })
+    }
+}
+
+impl Report for ExportReport {
+    fn print(&self) {
+        match &self.out {
+            Some(out) => println!("{} {} written to {} as {}", self.count, self.accounts, out, self.format),
+            None => print!("{}", self.export)
+        }
+    }
+}
+
+/// What submit, verify or update-status would have sent, from --dry-run
+#[derive(Serialize, Debug, Clone)]
+pub struct DryRunReport {
+    pub command : String,
+    // one for each transaction the command would have sent, in order
+    pub simulations : Vec<Simulation>
+}
+
+impl Report for DryRunReport {
+    fn print(&self) {
+        println!("dry run of {}, nothing was sent", self.command);
+        if self.simulations.is_empty() {
+            println!("no transactions would be sent");
+        }
+        for (index, simulation) in self.simulations.iter().enumerate() {
+            println!("transaction {}:", index);
+            simulation.print();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::UserEntry;
+
+    fn user_entry() -> UserEntry {
+        UserEntry {
+            user_account : "user_account".to_string(),
+            user : None,
+            status_code : 0,
+            description : "waiting".to_string(),
+            stage : VerificationStage::default(),
+            progress : None,
+            message : Some("a, b".to_string()),
+            timestamp : None
+        }
+    }
+
+    #[test]
+    fn printed_exports_carry_their_entries() {
+        let report = ExportReport::new("users", &[user_entry(), user_entry()], ExportFormat::Jsonl, None).unwrap();
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["count"], 2);
+        assert_eq!(json["out"], serde_json::Value::Null);
+        assert_eq!(json["entries"][1]["message"], "a, b");
+        assert!(json.get("export").is_none());
+        assert_eq!(report.export.lines().count(), 2);
+    }
+
+    #[test]
+    fn exports_to_a_file_report_only_a_status() {
+        let report = ExportReport::new("users", &[user_entry()], ExportFormat::Csv, Some("users.csv")).unwrap();
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["format"], "csv");
+        assert_eq!(json["out"], "users.csv");
+        assert!(json.get("entries").is_none());
+        // the header row and the one user
+        assert_eq!(report.export.lines().count(), 2);
+    }
+}
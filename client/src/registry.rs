@@ -0,0 +1,428 @@
+use crate::state::{self, Error, Result, Network, ProgramMetaData, UserMetaData, VerificationStage, VerifyInstruction, METADATA_ACCOUNT_SIZE, LEGACY_METADATA_ACCOUNT_SIZE, USER_ACCOUNT_SIZE, LEGACY_USER_ACCOUNT_SIZE, network_to_string};
+use crate::client::{VerifierClient, fetch_deployment, staleness};
+use crate::hash::to_hex;
+
+use clap::ValueEnum;
+use serde::Serialize;
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionConfig},
+    rpc_filter::RpcFilterType
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+use std::str::FromStr;
+
+// how many of an account's most recent transactions are searched for the instruction that names its program or user
+const IDENTIFY_SIGNATURE_LIMIT : usize = 20;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ScanAccounts {
+    Records,
+    Users
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Jsonl,
+    Csv
+}
+
+/// A verification record found by scanning the verifier program's accounts
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RecordEntry {
+    pub record_account : String,
+    // records don't hold the program they are for, so it is found from the transactions that wrote them
+    pub program : Option<String>,
+    pub cluster : Option<String>,
+    pub verified_code : u8,
+    pub description : String,
+    pub data_hash : String,
+    pub git_repo : String,
+    pub git_commit : String,
+    pub directory : String,
+    pub last_verified_slot : u64,
+    pub test_address : String,
+    // None if the program wasn't found, was never verified, or staleness wasn't checked
+    pub stale : Option<bool>
+}
+
+/// A user account found by scanning the verifier program's accounts
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct UserEntry {
+    pub user_account : String,
+    // found the same way as a record's program
+    pub user : Option<String>,
+    pub status_code : u8,
+    pub description : String,
+    pub stage : VerificationStage,
+    pub progress : Option<u8>,
+    pub message : Option<String>,
+    pub timestamp : Option<i64>
+}
+
+impl RecordEntry {
+    pub fn new(record_account : &Pubkey, program : Option<(Pubkey, Network)>, record : &ProgramMetaData, stale : Option<bool>) -> RecordEntry {
+
+        let source = record.source().unwrap_or_default();
+
+        RecordEntry {
+            record_account : record_account.to_string(),
+            program : program.map(|(program, _)| program.to_string()),
+            cluster : program.map(|(_, network)| network_to_string(network)),
+            verified_code : record.verified_code,
+            description : state::verified_code_description(record.verified_code).to_string(),
+            data_hash : to_hex(&record.data_hash),
+            git_repo : source.git_repo,
+            git_commit : source.git_commit,
+            directory : source.directory,
+            last_verified_slot : record.last_verified_slot,
+            test_address : record.test_address.to_string(),
+            stale : stale
+        }
+    }
+}
+
+impl UserEntry {
+    pub fn new(user_account : &Pubkey, user : Option<Pubkey>, user_data : &UserMetaData) -> UserEntry {
+
+        let latest = user_data.latest();
+
+        UserEntry {
+            user_account : user_account.to_string(),
+            user : user.map(|user| user.to_string()),
+            status_code : user_data.status_code,
+            description : state::status_code_description(user_data.status_code).to_string(),
+            stage : user_data.stage,
+            progress : latest.and_then(|latest| latest.progress),
+            message : latest.map(|latest| latest.log_message.clone()),
+            timestamp : latest.map(|latest| latest.timestamp)
+        }
+    }
+}
+
+/// A transaction's sol_verify instructions, each with the accounts it was given
+#[derive(Debug, Clone)]
+pub struct VerifierTransaction {
+    pub signature : String,
+    pub slot : u64,
+    pub block_time : Option<i64>,
+    pub instructions : Vec<(VerifyInstruction, Vec<Pubkey>)>,
+    // the positions of the sol_verify instructions that couldn't be decoded in either layout
+    pub undecoded : Vec<usize>
+}
+
+/// A submission or verification of a program, as recorded by a sol_verify transaction
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordEvent {
+    Submission {
+        signature : String,
+        slot : u64,
+        block_time : Option<i64>,
+        // the user that signed the submission
+        user : Option<String>,
+        git_repo : String,
+        git_commit : String,
+        directory : String
+    },
+    Verification {
+        signature : String,
+        slot : u64,
+        block_time : Option<i64>,
+        verified_code : u8,
+        description : String,
+        data_hash : String,
+        // slot the deployed program was last upgraded in when it was verified
+        verified_slot : u64,
+        test_address : String,
+        git_repo : String,
+        git_commit : String,
+        directory : String
+    }
+}
+
+impl RecordEvent {
+    pub fn slot(&self) -> u64 {
+        match self {
+            RecordEvent::Submission {slot, ..} | RecordEvent::Verification {slot, ..} => *slot
+        }
+    }
+
+    pub fn block_time(&self) -> Option<i64> {
+        match self {
+            RecordEvent::Submission {block_time, ..} | RecordEvent::Verification {block_time, ..} => *block_time
+        }
+    }
+}
+
+impl VerifierTransaction {
+    /// The submissions and verifications of program on network in this transaction
+    pub fn record_events(&self, program : &Pubkey, network : Network) -> Vec<RecordEvent> {
+
+        let mut events = Vec::new();
+        for (instruction, accounts) in self.instructions.iter() {
+            match instruction {
+                VerifyInstruction::SubmitProgram {metadata} if metadata.address == *program && metadata.network == network =>
+                    events.push(RecordEvent::Submission {
+                        signature : self.signature.clone(),
+                        slot : self.slot,
+                        block_time : self.block_time,
+                        user : accounts.first().map(|user| user.to_string()),
+                        git_repo : metadata.git_repo.clone(),
+                        git_commit : metadata.git_commit.clone(),
+                        directory : metadata.directory.clone()
+                    }),
+                VerifyInstruction::VerifyProgram {metadata} if metadata.real_address == *program && metadata.network == network =>
+                    events.push(RecordEvent::Verification {
+                        signature : self.signature.clone(),
+                        slot : self.slot,
+                        block_time : self.block_time,
+                        verified_code : metadata.verified_code,
+                        description : state::verified_code_description(metadata.verified_code).to_string(),
+                        data_hash : to_hex(&metadata.data_hash),
+                        verified_slot : metadata.verified_slot,
+                        test_address : metadata.test_address.to_string(),
+                        git_repo : metadata.git_repo.clone(),
+                        git_commit : metadata.git_commit.clone(),
+                        directory : metadata.directory.clone()
+                    }),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Rows of a csv export
+pub trait CsvRow {
+    const HEADER : &'static [&'static str];
+    fn fields(&self) -> Vec<String>;
+}
+
+fn optional<T : ToString>(value : &Option<T>) -> String {
+    value.as_ref().map_or(String::new(), |v| v.to_string())
+}
+
+impl CsvRow for RecordEntry {
+    const HEADER : &'static [&'static str] = &["record_account", "program", "cluster", "verified_code", "description", "data_hash",
+        "git_repo", "git_commit", "directory", "last_verified_slot", "test_address", "stale"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.record_account.clone(), optional(&self.program), optional(&self.cluster), self.verified_code.to_string(),
+            self.description.clone(), self.data_hash.clone(), self.git_repo.clone(), self.git_commit.clone(), self.directory.clone(),
+            self.last_verified_slot.to_string(), self.test_address.clone(), optional(&self.stale)]
+    }
+}
+
+impl CsvRow for UserEntry {
+    const HEADER : &'static [&'static str] = &["user_account", "user", "status_code", "description", "stage", "progress", "message", "timestamp"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.user_account.clone(), optional(&self.user), self.status_code.to_string(), self.description.clone(),
+            self.stage.name().to_string(), optional(&self.progress), optional(&self.message), optional(&self.timestamp)]
+    }
+}
+
+fn csv_field(field : &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    field.to_string()
+}
+
+/// entries as json lines or as csv with a header row
+pub fn export<T : Serialize + CsvRow>(entries : &[T], format : ExportFormat) -> Result<String> {
+
+    let mut lines = Vec::with_capacity(entries.len() + 1);
+    match format {
+        ExportFormat::Jsonl => {
+            for entry in entries {
+                lines.push(serde_json::to_string(entry)?);
+            }
+        },
+        ExportFormat::Csv => {
+            lines.push(T::HEADER.join(","));
+            for entry in entries {
+                lines.push(entry.fields().iter().map(|field| csv_field(field)).collect::<Vec<String>>().join(","));
+            }
+        }
+    }
+
+    Ok(lines.iter().map(|line| line.to_string() + "\n").collect())
+}
+
+impl VerifierClient {
+
+    // the verifier program's accounts of any of these sizes, one query each as a filter can only match one size
+    async fn accounts_of_size(&self, sizes : &[usize]) -> Result<Vec<(Pubkey, Vec<u8>)>> {
+
+        let mut found = Vec::new();
+        for size in sizes.iter() {
+            let config = RpcProgramAccountsConfig {
+                filters : Some(vec![RpcFilterType::DataSize(*size as u64)]),
+                account_config : RpcAccountInfoConfig {encoding : Some(UiAccountEncoding::Base64), ..RpcAccountInfoConfig::default()},
+                ..RpcProgramAccountsConfig::default()
+            };
+
+            let accounts = self.rpc().get_program_accounts_with_config(&self.program_id(), config).await?;
+            found.extend(accounts.into_iter().map(|(address, account)| (address, account.data)));
+        }
+
+        Ok(found)
+    }
+
+    /// The sol_verify instructions in a transaction, None if it couldn't be decoded
+    pub async fn verifier_transaction(&self, signature : &Signature) -> Result<Option<VerifierTransaction>> {
+
+        let config = RpcTransactionConfig {encoding : Some(UiTransactionEncoding::Base64), commitment : None, max_supported_transaction_version : Some(0)};
+        let confirmed = self.rpc().get_transaction_with_config(signature, config).await?;
+        let transaction = match confirmed.transaction.transaction.decode() {
+            Some(transaction) => transaction,
+            None => return Ok(None)
+        };
+
+        let keys = transaction.message.static_account_keys();
+        let mut instructions = Vec::new();
+        let mut undecoded = Vec::new();
+        for (index, instruction) in transaction.message.instructions().iter().enumerate() {
+            if keys.get(instruction.program_id_index as usize) != Some(&self.program_id()) {
+                continue;
+            }
+            let accounts : Vec<Pubkey> = instruction.accounts.iter().filter_map(|index| keys.get(*index as usize).copied()).collect();
+            match VerifyInstruction::unpack(&instruction.data) {
+                Ok(decoded) => instructions.push((decoded, accounts)),
+                Err(_) => undecoded.push(index)
+            }
+        }
+
+        Ok(Some(VerifierTransaction {
+            signature : signature.to_string(),
+            slot : confirmed.slot,
+            block_time : confirmed.block_time,
+            instructions : instructions,
+            undecoded : undecoded
+        }))
+    }
+
+    // searches an account's recent transactions for a verifier instruction that identify accepts
+    async fn identify<T>(&self, account : &Pubkey, identify : impl Fn(&VerifyInstruction, &[Pubkey]) -> Option<T>) -> Result<Option<T>> {
+
+        let config = GetConfirmedSignaturesForAddress2Config {limit : Some(IDENTIFY_SIGNATURE_LIMIT), ..GetConfirmedSignaturesForAddress2Config::default()};
+        let signatures = self.rpc().get_signatures_for_address_with_config(account, config).await?;
+
+        for status in signatures.iter().filter(|status| status.err.is_none()) {
+
+            let signature = Signature::from_str(&status.signature).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+            let transaction = match self.verifier_transaction(&signature).await? {
+                Some(transaction) => transaction,
+                None => continue
+            };
+
+            for (instruction, accounts) in transaction.instructions.iter() {
+                if let Some(found) = identify(instruction, accounts) {
+                    return Ok(Some(found));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Submissions and verifications of a program from the most recent limit transactions on its record, oldest first
+    pub async fn record_events(&self, program : &Pubkey, network : Network, limit : usize) -> Result<Vec<RecordEvent>> {
+
+        let config = GetConfirmedSignaturesForAddress2Config {limit : Some(limit), ..GetConfirmedSignaturesForAddress2Config::default()};
+        let signatures = self.rpc().get_signatures_for_address_with_config(&self.metadata_address(program, network), config).await?;
+
+        let mut events = Vec::new();
+        for status in signatures.iter().rev().filter(|status| status.err.is_none()) {
+            let signature = Signature::from_str(&status.signature).map_err(|err| Error::InvalidConfig(err.to_string()))?;
+            if let Some(transaction) = self.verifier_transaction(&signature).await? {
+                events.extend(transaction.record_events(program, network));
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Every verification record held by the verifier program.  With check_staleness each program is also looked up
+    /// on its cluster to see whether it was upgraded after it was verified
+    pub async fn scan_records(&self, check_staleness : bool) -> Result<Vec<RecordEntry>> {
+
+        let mut entries = Vec::new();
+
+        for (address, data) in self.accounts_of_size(&[METADATA_ACCOUNT_SIZE, LEGACY_METADATA_ACCOUNT_SIZE]).await? {
+
+            let record = match ProgramMetaData::unpack(&data) {
+                Ok(record) => record,
+                Err(_) => continue
+            };
+
+            // the address and network are only accepted if they derive this record's address
+            let program = self.identify(&address, |instruction, _| {
+                let (program, network) = match instruction {
+                    VerifyInstruction::SubmitProgram {metadata} => (metadata.address, metadata.network),
+                    VerifyInstruction::VerifyProgram {metadata} => (metadata.real_address, metadata.network),
+                    _ => return None
+                };
+                (self.metadata_address(&program, network) == address).then_some((program, network))
+            }).await?;
+
+            let stale = match program {
+                Some((program, network)) if check_staleness => fetch_deployment(&self.program_client(network), &program).await.ok()
+                    .and_then(|deployment| staleness(record.verified_code, record.last_verified_slot, &deployment)),
+                _ => None
+            };
+
+            entries.push(RecordEntry::new(&address, program, &record, stale));
+        }
+
+        Ok(entries)
+    }
+
+    /// Every user account held by the verifier program, with each user's latest status
+    pub async fn scan_users(&self) -> Result<Vec<UserEntry>> {
+
+        let mut entries = Vec::new();
+
+        for (address, data) in self.accounts_of_size(&[USER_ACCOUNT_SIZE, LEGACY_USER_ACCOUNT_SIZE]).await? {
+
+            let user_data = match UserMetaData::unpack(&data) {
+                Ok(user_data) => user_data,
+                Err(_) => continue
+            };
+
+            // a submission is signed by the user, a status update names them
+            let user = self.identify(&address, |instruction, accounts| {
+                let user = match instruction {
+                    VerifyInstruction::SubmitProgram {..} => *accounts.first()?,
+                    VerifyInstruction::UpdateStatus {metadata} => metadata.user_pubkey,
+                    _ => return None
+                };
+                (self.user_address(&user) == address).then_some(user)
+            }).await?;
+
+            entries.push(UserEntry::new(&address, user, &user_data));
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+}
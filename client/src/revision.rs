@@ -0,0 +1,169 @@
+use crate::state::{Error, Result};
+use crate::manifest::is_commit_hash;
+
+use serde::Serialize;
+use solana_security_txt::SecurityTxt;
+use std::collections::HashMap;
+use tokio::process::Command;
+
+/// The repo and commit a verification should build from once the program's own security.txt has been taken into account
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PinnedSource {
+    pub git_repo : String,
+    pub git_commit : String,
+    pub directory : String,
+    // the revision or release named in security.txt, if it named one
+    pub declared : Option<String>
+}
+
+// the refs of a remote repo by name, as listed by git ls-remote.  git runs as a tokio process so that waiting on the
+// network doesn't hold up the runtime, which the API server shares between requests
+async fn ls_remote(git_repo : &str) -> Option<HashMap<String, String>> {
+
+    let output = Command::new("git").args(["ls-remote", git_repo]).output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(hash, name)| (name.to_string(), hash.to_string()))
+        .collect())
+}
+
+// a tag or branch name to the commit it points at.  Annotated tags are listed twice, the ^{} entry being the commit
+fn resolve_ref(refs : &HashMap<String, String>, reference : &str) -> Option<String> {
+
+    if is_commit_hash(reference) {
+        return Some(reference.to_lowercase());
+    }
+
+    let name = reference.strip_prefix("refs/tags/").or_else(|| reference.strip_prefix("refs/heads/")).unwrap_or(reference);
+    [format!("refs/tags/{}^{{}}", name), format!("refs/tags/{}", name), format!("refs/heads/{}", name)].iter()
+        .find_map(|full_name| refs.get(full_name).cloned())
+}
+
+// an archive of a repo usually extracts to a single folder named after the repo and the ref, such as sol_verify-main, that a
+// clone of the repo doesn't have.  That folder is dropped from the start of directory
+fn strip_archive_folder(directory : &str, git_repo : &str) -> String {
+
+    let name = git_repo.trim_end_matches('/').rsplit('/').next().unwrap_or_default().trim_end_matches(".git");
+    let (folder, rest) = directory.split_once('/').unwrap_or((directory, ""));
+
+    if !name.is_empty() && folder.starts_with(&format!("{}-", name)) {
+        rest.to_string()
+    }
+    else {
+        directory.to_string()
+    }
+}
+
+// either may be abbreviated, so they match if one starts with the other
+fn commits_match(first : &str, second : &str) -> bool {
+    let (first, second) = (first.to_lowercase(), second.to_lowercase());
+    first.starts_with(&second) || second.starts_with(&first)
+}
+
+/// Prefer the commit given by source_revision, or the tag given by source_release, over the one supplied by the user.
+/// The declared revision is resolved against the user's repo, falling back to security.txt's source_code when the
+/// repo is an archive that can't be listed, in which case the archive's top level folder is dropped from directory.
+/// A user supplied commit that disagrees with the declared one is an error
+pub async fn pin_source(git_repo : &str, git_commit : &str, directory : &str, security_txt : &SecurityTxt) -> Result<PinnedSource> {
+
+    let non_empty = |value : &Option<String>| value.clone().filter(|v| !v.trim().is_empty());
+
+    let declared = match non_empty(&security_txt.source_revision).or_else(|| non_empty(&security_txt.source_release)) {
+        Some(declared) => declared.trim().to_string(),
+        None => return Ok(PinnedSource {git_repo : git_repo.to_string(), git_commit : git_commit.to_string(), directory : directory.to_string(), declared : None})
+    };
+
+    let mut listed = None;
+    for repo in std::iter::once(git_repo.to_string()).chain(non_empty(&security_txt.source_code)) {
+        if let Some(refs) = ls_remote(&repo).await {
+            listed = Some((repo, refs));
+            break;
+        }
+    }
+
+    let (repo, refs) = match listed {
+        Some(listed) => listed,
+        None => return Err(match Command::new("git").arg("--version").output().await {
+            Ok(_) => Error::GitError(format!("could not list {} to resolve {} from security.txt", git_repo, declared)),
+            Err(_) => Error::GitError(format!("git was not found, so {} from security.txt could not be resolved", declared))
+        })
+    };
+
+    let declared_commit = resolve_ref(&refs, &declared)
+        .ok_or_else(|| Error::UnknownRevision {declared : declared.clone(), repo : repo.clone()})?;
+
+    if !git_commit.is_empty() && !commits_match(git_commit, &declared_commit) {
+        return Err(Error::SourceRevisionMismatch {declared : declared, resolved : declared_commit, given : git_commit.to_string()});
+    }
+
+    // keep whichever of the two is the more complete hash
+    let git_commit = if git_commit.len() > declared_commit.len() { git_commit.to_string() } else { declared_commit };
+
+    let directory = if repo == git_repo { directory.to_string() } else { strip_archive_folder(directory, &repo) };
+
+    Ok(PinnedSource {git_repo : repo, git_commit : git_commit, directory : directory, declared : Some(declared)})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_folder_is_dropped_for_the_repo_it_came_from() {
+        let repo = "https://github.com/daoplays/sol_verify";
+        assert_eq!(strip_archive_folder("sol_verify-main/program", repo), "program");
+        assert_eq!(strip_archive_folder("sol_verify-v0.5/program", &format!("{}.git/", repo)), "program");
+        assert_eq!(strip_archive_folder("sol_verify-main", repo), "");
+    }
+
+    #[test]
+    fn other_directories_are_kept() {
+        let repo = "https://github.com/daoplays/sol_verify";
+        assert_eq!(strip_archive_folder("program", repo), "program");
+        assert_eq!(strip_archive_folder("other-main/program", repo), "other-main/program");
+        assert_eq!(strip_archive_folder("", repo), "");
+    }
+
+    fn security_txt(source_release : &str) -> SecurityTxt {
+        SecurityTxt {
+            name : "test".to_string(),
+            project_url : String::new(),
+            contacts : Vec::new(),
+            policy : String::new(),
+            preferred_languages : Vec::new(),
+            source_code : None,
+            source_release : Some(source_release.to_string()),
+            source_revision : None,
+            encryption : None,
+            auditors : Vec::new(),
+            acknowledgements : None,
+            expiry : None
+        }
+    }
+
+    // a repo with one commit, tagged v1, run through the same tokio runtime the client uses
+    #[tokio::test]
+    async fn releases_are_pinned_to_their_commit() {
+        let repo = std::env::temp_dir().join(format!("sol_verify_pin_source_{}", std::process::id()));
+        let git = |args : &[&str]| std::process::Command::new("git").arg("-C").arg(&repo).args(args).output().unwrap();
+        std::fs::create_dir_all(&repo).unwrap();
+        git(&["init", "-q"]);
+        git(&["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-q", "--allow-empty", "-m", "release"]);
+        git(&["tag", "v1"]);
+        let commit = String::from_utf8(git(&["rev-parse", "HEAD"]).stdout).unwrap().trim().to_string();
+        let url = repo.to_string_lossy().to_string();
+
+        let pinned = pin_source(&url, "", "program", &security_txt("v1")).await;
+        let unknown = pin_source(&url, "", "program", &security_txt("v2")).await;
+        let mismatch = pin_source(&url, "abcdef0", "program", &security_txt("v1")).await;
+        std::fs::remove_dir_all(&repo).unwrap();
+
+        assert_eq!(pinned.unwrap(), PinnedSource {git_repo : url, git_commit : commit, directory : "program".to_string(), declared : Some("v1".to_string())});
+        assert!(matches!(unknown, Err(Error::UnknownRevision {..})));
+        assert!(matches!(mismatch, Err(Error::SourceRevisionMismatch {..})));
+    }
+}
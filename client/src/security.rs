@@ -0,0 +1,263 @@
+use serde::Serialize;
+use solana_security_txt::{Contact, SecurityTxt, SecurityTxtError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ContactReport {
+    pub kind : String,
+    pub value : String,
+    pub valid : bool
+}
+
+/// Everything in a program's security.txt, along with any problems found with it
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct SecurityReport {
+    pub address : String,
+    pub upgradeable : bool,
+    pub found : bool,
+    pub name : Option<String>,
+    pub project_url : Option<String>,
+    pub contacts : Vec<ContactReport>,
+    pub policy : Option<String>,
+    pub preferred_languages : Vec<String>,
+    pub source_code : Option<String>,
+    pub source_release : Option<String>,
+    pub source_revision : Option<String>,
+    pub encryption : Option<String>,
+    pub auditors : Vec<String>,
+    pub acknowledgements : Option<String>,
+    pub expiry : Option<String>,
+    pub expired : Option<bool>,
+    pub problems : Vec<String>
+}
+
+/// The parts of a security.txt an explorer shows next to a verification record
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SecuritySummary {
+    pub found : bool,
+    pub name : Option<String>,
+    pub project_url : Option<String>,
+    pub source_code : Option<String>,
+    // source_revision if there is one, otherwise source_release
+    pub source_revision : Option<String>,
+    pub expired : Option<bool>,
+    pub problems : usize
+}
+
+impl From<&SecurityReport> for SecuritySummary {
+    fn from(report : &SecurityReport) -> Self {
+        SecuritySummary {
+            found : report.found,
+            name : report.name.clone(),
+            project_url : report.project_url.clone(),
+            source_code : report.source_code.clone(),
+            source_revision : report.source_revision.clone().or_else(|| report.source_release.clone()),
+            expired : report.expired,
+            problems : report.problems.len()
+        }
+    }
+}
+
+fn is_email(value : &str) -> bool {
+    match value.split_once('@') {
+        Some((user, domain)) => !user.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.') && !value.contains(char::is_whitespace),
+        None => false
+    }
+}
+
+fn is_link(value : &str) -> bool {
+    (value.starts_with("https://") || value.starts_with("http://")) && !value.contains(char::is_whitespace)
+}
+
+// twitter and telegram handles, with or without the leading @
+fn is_handle(value : &str, min_len : usize, max_len : usize) -> bool {
+    let handle = value.strip_prefix('@').unwrap_or(value);
+    (min_len..=max_len).contains(&handle.len()) && handle.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn check_contact(contact : &Contact) -> ContactReport {
+    let (kind, value, valid) = match contact {
+        Contact::Email(value) => ("email", value, is_email(value)),
+        Contact::Link(value) => ("link", value, is_link(value)),
+        Contact::Twitter(value) => ("twitter", value, is_handle(value, 1, 15)),
+        Contact::Telegram(value) => ("telegram", value, is_handle(value, 5, 32)),
+        // discord names and other: contacts, which the security.txt spec allows for anything else, have no format to check
+        Contact::Discord(value) => ("discord", value, !value.trim().is_empty()),
+        Contact::Other(value) => ("other", value, !value.trim().is_empty())
+    };
+    ContactReport {kind : kind.to_string(), value : value.clone(), valid : valid}
+}
+
+// days since 1970-01-01 of a YYYY-MM-DD date, using the days from civil algorithm
+fn days_since_epoch(date : &str) -> Option<i64> {
+    let mut parts = date.split('-');
+    let year : i64 = parts.next()?.parse().ok()?;
+    let month : i64 = parts.next()?.parse().ok()?;
+    let day : i64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+fn today() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64 / 86400)
+}
+
+impl SecurityReport {
+
+    pub fn new(address : String, upgradeable : bool, security_txt : std::result::Result<SecurityTxt, SecurityTxtError>) -> SecurityReport {
+
+        let mut report = SecurityReport {address : address, upgradeable : upgradeable, ..SecurityReport::default()};
+
+        let security_txt = match security_txt {
+            Ok(security_txt) => security_txt,
+            Err(SecurityTxtError::StartNotFound) => {
+                report.problems.push("no security.txt found in the program".to_string());
+                return report;
+            },
+            Err(err) => {
+                report.problems.push(format!("security.txt could not be parsed: {}", err));
+                return report;
+            }
+        };
+
+        report.found = true;
+
+        for (field, value) in [("name", &security_txt.name), ("project_url", &security_txt.project_url), ("policy", &security_txt.policy)] {
+            if value.trim().is_empty() {
+                report.problems.push(format!("required field {} is empty", field));
+            }
+        }
+
+        if security_txt.contacts.is_empty() {
+            report.problems.push("required field contacts is empty".to_string());
+        }
+
+        report.contacts = security_txt.contacts.iter().map(check_contact).collect();
+        for contact in report.contacts.iter().filter(|c| !c.valid) {
+            report.problems.push(format!("contact {}:{} is not valid", contact.kind, contact.value));
+        }
+
+        if !is_link(&security_txt.project_url) {
+            report.problems.push(format!("project_url {} is not a link", security_txt.project_url));
+        }
+
+        if let Some(expiry) = &security_txt.expiry {
+            match days_since_epoch(expiry) {
+                Some(days) => {
+                    let expired = days < today();
+                    if expired {
+                        report.problems.push(format!("security.txt expired on {}", expiry));
+                    }
+                    report.expired = Some(expired);
+                },
+                None => report.problems.push(format!("expiry {} is not a YYYY-MM-DD date", expiry))
+            }
+        }
+
+        report.name = Some(security_txt.name);
+        report.project_url = Some(security_txt.project_url);
+        report.policy = Some(security_txt.policy);
+        // the parser gives a single empty entry for lists that weren't set
+        report.preferred_languages = security_txt.preferred_languages.into_iter().filter(|s| !s.is_empty()).collect();
+        report.source_code = security_txt.source_code;
+        report.source_release = security_txt.source_release;
+        report.source_revision = security_txt.source_revision;
+        report.encryption = security_txt.encryption;
+        report.auditors = security_txt.auditors.into_iter().filter(|s| !s.is_empty()).collect();
+        report.acknowledgements = security_txt.acknowledgements;
+        report.expiry = security_txt.expiry;
+
+        report
+    }
+
+    pub fn print(&self) {
+
+        let field = |name : &str, value : &Option<String>| {
+            if let Some(value) = value {
+                println!("{}: {}", name, value);
+            }
+        };
+
+        println!("program: {}", self.address);
+        println!("upgradeable: {}", self.upgradeable);
+
+        if self.found {
+            field("name", &self.name);
+            field("project url", &self.project_url);
+            for contact in &self.contacts {
+                println!("contact: {} {}{}", contact.kind, contact.value, if contact.valid { "" } else { " (invalid)" });
+            }
+            field("policy", &self.policy);
+            if !self.preferred_languages.is_empty() {
+                println!("preferred languages: {}", self.preferred_languages.join(", "));
+            }
+            field("source code", &self.source_code);
+            field("source release", &self.source_release);
+            field("source revision", &self.source_revision);
+            field("encryption", &self.encryption);
+            for auditor in &self.auditors {
+                println!("auditor: {}", auditor);
+            }
+            field("acknowledgements", &self.acknowledgements);
+            field("expiry", &self.expiry);
+        }
+
+        if self.problems.is_empty() {
+            println!("security.txt is valid");
+        }
+        for problem in &self.problems {
+            println!("problem: {}", problem);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid(contact : Contact) -> bool {
+        check_contact(&contact).valid
+    }
+
+    #[test]
+    fn contacts_are_checked_by_kind() {
+        assert!(valid(Contact::Email("security@example.com".to_string())));
+        assert!(!valid(Contact::Email("security@localhost".to_string())));
+        assert!(!valid(Contact::Email("@example.com".to_string())));
+
+        assert!(valid(Contact::Link("https://example.com/security".to_string())));
+        assert!(!valid(Contact::Link("example.com/security".to_string())));
+
+        assert!(valid(Contact::Twitter("@daoplays".to_string())));
+        assert!(!valid(Contact::Twitter("a_handle_that_is_too_long".to_string())));
+        assert!(valid(Contact::Telegram("daoplays".to_string())));
+        assert!(!valid(Contact::Telegram("dao".to_string())));
+
+        assert!(valid(Contact::Discord("daoplays#1234".to_string())));
+        assert!(!valid(Contact::Discord(" ".to_string())));
+    }
+
+    #[test]
+    fn other_contacts_are_valid_unless_empty() {
+        let report = check_contact(&Contact::Other("matrix: @daoplays:matrix.org".to_string()));
+        assert_eq!(report.kind, "other");
+        assert!(report.valid);
+        assert!(!valid(Contact::Other("".to_string())));
+    }
+
+    #[test]
+    fn dates_count_days_from_the_epoch() {
+        assert_eq!(days_since_epoch("1970-01-01"), Some(0));
+        assert_eq!(days_since_epoch("2000-03-01"), Some(11017));
+        assert_eq!(days_since_epoch("2024-13-01"), None);
+        assert_eq!(days_since_epoch("2024-01"), None);
+    }
+}
@@ -0,0 +1,214 @@
+//! Simulates the transactions a command would send, for --dry-run.  Nothing is sent: the transaction is signed and
+//! run with simulateTransaction, and its instructions, logs, compute units and the changes it would make to each
+//! writable account are decoded
+
+use crate::state::{Error, Result, ProgramMetaData, UserMetaData, METADATA_ACCOUNT_SIZE, LEGACY_METADATA_ACCOUNT_SIZE, USER_ACCOUNT_SIZE, LEGACY_USER_ACCOUNT_SIZE};
+use crate::client::VerifierClient;
+use crate::registry::{RecordEntry, UserEntry};
+use crate::decode::{AccountRef, DecodedInstruction, describe_logs, describe_transaction_error, print_instruction};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{account::Account, transaction::Transaction};
+use solana_transaction_status::UiTransactionEncoding;
+
+/// How a transaction would change an account it writes to
+#[derive(Serialize, Debug, Clone)]
+pub struct AccountChange {
+    pub address : String,
+    // the role an instruction gives the account, None for accounts only the transaction uses
+    pub role : Option<String>,
+    // None when the account doesn't exist before, or after, the transaction
+    pub lamports_before : Option<u64>,
+    pub lamports_after : Option<u64>,
+    pub size_before : Option<usize>,
+    pub size_after : Option<usize>,
+    // the fields of a record or user account that change, as "field: before -> after"
+    pub fields : Vec<String>
+}
+
+/// The result of simulating a transaction
+#[derive(Serialize, Debug, Clone)]
+pub struct Simulation {
+    pub instructions : Vec<DecodedInstruction>,
+    // None if the transaction would succeed
+    pub error : Option<String>,
+    pub units_consumed : Option<u64>,
+    pub accounts : Vec<AccountChange>,
+    pub logs : Vec<String>
+}
+
+impl Simulation {
+    pub fn print(&self) {
+        match &self.error {
+            Some(error) => println!("would fail: {}", error),
+            None => println!("would succeed")
+        }
+        if let Some(units) = self.units_consumed {
+            println!("compute units: {}", units);
+        }
+        for instruction in self.instructions.iter() {
+            print_instruction(instruction);
+        }
+        if !self.accounts.is_empty() {
+            println!("account changes:");
+        }
+        for account in self.accounts.iter() {
+            println!("  {}: {}", account.role.as_deref().unwrap_or("fee payer"), account.address);
+            if account.lamports_before != account.lamports_after {
+                println!("    lamports: {} -> {}", show(account.lamports_before), show(account.lamports_after));
+            }
+            if account.size_before != account.size_after {
+                println!("    size: {} -> {}", show(account.size_before), show(account.size_after));
+            }
+            for field in account.fields.iter() {
+                println!("    {}", field);
+            }
+        }
+        if !self.logs.is_empty() {
+            println!("logs:");
+            for log in self.logs.iter() {
+                println!("  {}", log);
+            }
+        }
+    }
+}
+
+fn show<T : std::fmt::Display>(value : Option<T>) -> String {
+    value.map_or("none".to_string(), |value| value.to_string())
+}
+
+// the fields of an account as a record or user, empty for any other account
+fn account_fields(address : &Pubkey, account : Option<&Account>, program_id : &Pubkey) -> Map<String, Value> {
+
+    let value = match account.filter(|account| account.owner == *program_id).map(|account| (account.data.len(), &account.data)) {
+        Some((METADATA_ACCOUNT_SIZE | LEGACY_METADATA_ACCOUNT_SIZE, data)) => ProgramMetaData::unpack(data)
+            .ok()
+            .and_then(|record| serde_json::to_value(RecordEntry::new(address, None, &record, None)).ok()),
+        Some((USER_ACCOUNT_SIZE | LEGACY_USER_ACCOUNT_SIZE, data)) => UserMetaData::unpack(data)
+            .ok()
+            .and_then(|user| serde_json::to_value(UserEntry::new(address, None, &user)).ok()),
+        _ => None
+    };
+
+    match value {
+        Some(Value::Object(fields)) => fields,
+        _ => Map::new()
+    }
+}
+
+// the fields that differ between two decodings of an account, leaving out the account's own address and the empty
+// fields of a new account
+fn changed_fields(before : &Map<String, Value>, after : &Map<String, Value>) -> Vec<String> {
+    let mut names : Vec<&String> = before.keys().chain(after.keys().filter(|name| !before.contains_key(*name))).collect();
+    names.retain(|name| !name.ends_with("_account") && before.get(*name).unwrap_or(&Value::Null) != after.get(*name).unwrap_or(&Value::Null));
+    names.into_iter().map(|name| format!("{}: {} -> {}", name, show(before.get(name)), show(after.get(name)))).collect()
+}
+
+impl VerifierClient {
+
+    /// Simulates a signed transaction, decoding its sol_verify instructions and how it would change each account it writes to
+    pub async fn simulate(&self, transaction : &Transaction) -> Result<Simulation> {
+
+        let message = &transaction.message;
+        let program_id = self.program_id();
+
+        let keys : Vec<AccountRef> = message.account_keys.iter().enumerate()
+            .map(|(index, key)| AccountRef {address : *key, signer : message.is_signer(index), writable : message.is_writable(index)})
+            .collect();
+        let writable : Vec<Pubkey> = keys.iter().filter(|key| key.writable).map(|key| key.address).collect();
+
+        let instructions : Vec<DecodedInstruction> = message.instructions.iter().enumerate()
+            .filter(|(_, instruction)| message.account_keys.get(instruction.program_id_index as usize) == Some(&program_id))
+            .map(|(index, instruction)| {
+                let accounts : Vec<AccountRef> = instruction.accounts.iter().filter_map(|index| keys.get(*index as usize).cloned()).collect();
+                self.decode_instruction(index, None, &instruction.data, &accounts)
+            })
+            .collect();
+        let verifier_instructions : Vec<bool> = message.instructions.iter()
+            .map(|instruction| message.account_keys.get(instruction.program_id_index as usize) == Some(&program_id))
+            .collect();
+
+        let before = self.rpc().get_multiple_accounts(&writable).await?;
+
+        let config = RpcSimulateTransactionConfig {
+            sig_verify : true,
+            replace_recent_blockhash : false,
+            commitment : Some(self.rpc().commitment()),
+            encoding : Some(UiTransactionEncoding::Base64),
+            accounts : Some(RpcSimulateTransactionAccountsConfig {
+                encoding : Some(UiAccountEncoding::Base64),
+                addresses : writable.iter().map(|address| address.to_string()).collect()
+            }),
+            min_context_slot : None
+        };
+        let result = self.rpc().simulate_transaction_with_config(transaction, config).await?.value;
+
+        // a failed simulation doesn't return the accounts, as nothing would change
+        let after : Vec<Option<Account>> = match result.accounts {
+            Some(accounts) => accounts.iter().map(|account| account.as_ref().and_then(|account| account.decode())).collect(),
+            None => before.clone()
+        };
+        if after.len() != writable.len() {
+            return Err(Error::InvalidConfig(format!("simulation returned {} accounts for {} requested", after.len(), writable.len())));
+        }
+
+        let accounts = writable.iter().zip(before.iter().zip(after.iter())).map(|(address, (before, after))| {
+            let role = instructions.iter().flat_map(|instruction| instruction.accounts.iter())
+                .find(|account| account.address == address.to_string())
+                .and_then(|account| account.role.clone());
+            AccountChange {
+                address : address.to_string(),
+                role : role,
+                lamports_before : before.as_ref().map(|account| account.lamports),
+                lamports_after : after.as_ref().map(|account| account.lamports),
+                size_before : before.as_ref().map(|account| account.data.len()),
+                size_after : after.as_ref().map(|account| account.data.len()),
+                fields : changed_fields(&account_fields(address, before.as_ref(), &program_id), &account_fields(address, after.as_ref(), &program_id))
+            }
+        }).collect();
+
+        Ok(Simulation {
+            instructions : instructions,
+            error : result.err.as_ref().map(|err| describe_transaction_error(err, &verifier_instructions)),
+            units_consumed : result.units_consumed,
+            accounts : accounts,
+            logs : describe_logs(&result.logs.unwrap_or_default(), &program_id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fields(value : Value) -> Map<String, Value> {
+        match value {
+            Value::Object(fields) => fields,
+            _ => panic!("not an object")
+        }
+    }
+
+    #[test]
+    fn only_changed_fields_are_listed() {
+        let before = fields(json!({"user_account" : "a", "status_code" : 0, "message" : "queued", "stage" : "queued"}));
+        let after = fields(json!({"user_account" : "b", "status_code" : 1, "message" : "queued", "stage" : "building"}));
+        assert_eq!(changed_fields(&before, &after), vec!["stage: \"queued\" -> \"building\"", "status_code: 0 -> 1"]);
+    }
+
+    #[test]
+    fn new_and_missing_fields_are_listed() {
+        let before = fields(json!({"progress" : 10, "removed" : true}));
+        let after = fields(json!({"progress" : 10, "added" : "x"}));
+        assert_eq!(changed_fields(&before, &after), vec!["removed: true -> none", "added: none -> \"x\""]);
+
+        // a field that is null on one side and missing on the other hasn't changed
+        let before = fields(json!({"timestamp" : null}));
+        assert!(changed_fields(&before, &Map::new()).is_empty());
+        assert!(changed_fields(&Map::new(), &Map::new()).is_empty());
+    }
+}
@@ -1,8 +1,8 @@
 use thiserror::Error;
 use borsh::{BorshDeserialize, BorshSerialize};
+use serde::Serialize;
 
 use solana_program::{pubkey::Pubkey};
-use serde::{Deserialize, Serialize};
 
 
 #[derive(Error, Debug)]
@@ -16,15 +16,73 @@ pub enum Error {
     #[error("serialization error: ({0})")]
     SerializationError(std::io::Error),
 
+    // boxed, as it is several times the size of every other variant and would make every Result that large
     #[error("solana client error: ({0})")]
-    ClientError(#[from] solana_client::client_error::ClientError),
+    ClientError(Box<solana_client::client_error::ClientError>),
 
     #[error("error in public key derivation: ({0})")]
     KeyDerivationError(#[from] solana_sdk::pubkey::PubkeyError),
+
+    #[error("invalid user account data")]
+    InvalidUserData,
+
+    #[error("invalid record account data of {0} bytes")]
+    InvalidRecordData(usize),
+
+    #[error("invalid submission: {0}")]
+    InvalidSubmission(String),
+
+    #[error("invalid program account: {0}")]
+    InvalidProgramAccount(String),
+
+    #[error("json error: ({0})")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("failed to disassemble program: ({0})")]
+    DisassemblyError(String),
+
+    #[error("failed to read {0}: ({1})")]
+    FileReadError(String, std::io::Error),
+
+    #[error("git error: ({0})")]
+    GitError(String),
+
+    #[error("index error: ({0})")]
+    IndexError(#[from] rusqlite::Error),
+
+    #[error("{declared} from security.txt is not a commit, tag or branch of {repo}")]
+    UnknownRevision {declared : String, repo : String},
+
+    #[error("security.txt declares source revision {declared} ({resolved}) but commit {given} was given")]
+    SourceRevisionMismatch {declared : String, resolved : String, given : String},
+
+    #[error("transaction {signature} failed: {reason}")]
+    TransactionFailed {signature : String, reason : String},
+
+    #[error("transaction {signature} expired before reaching {commitment} commitment, after {attempts} attempts.  It can't land now, so it is safe to try again")]
+    TransactionExpired {signature : String, commitment : String, attempts : usize},
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+impl From<solana_client::client_error::ClientError> for Error {
+    fn from(err : solana_client::client_error::ClientError) -> Self {
+        Error::ClientError(Box::new(err))
+    }
+}
+
+impl Error {
+    /// The status code the verifier sends the user for this error, None if it isn't one the user is told about
+    pub fn status_code(&self) -> Option<u8> {
+        match self {
+            Error::GitError(_) => Some(GIT_REPO_DOESNT_EXIST),
+            Error::UnknownRevision {..} => Some(GIT_COMMIT_DOESNT_EXIST),
+            Error::SourceRevisionMismatch {..} => Some(SOURCE_REVISION_MISMATCH),
+            _ => None
+        }
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Copy)]
 pub enum Network {
     TestNet,
@@ -33,6 +91,31 @@ pub enum Network {
     Invalid
 }
 
+pub fn network_to_string(network :  Network) ->  String 
+{
+    match network {
+        Network::TestNet => "test_net".to_string(),
+        Network::DevNet => "dev_net".to_string(),
+        Network::MainNet => "main_net".to_string(),
+        Network::Invalid => "invalid".to_string()
+
+    }
+}
+
+impl std::str::FromStr for Network {
+    type Err = Error;
+
+    // accepts the names used in the program derived addresses, the usual cluster names, or the enum index
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "0" | "test_net" | "testnet" => Ok(Network::TestNet),
+            "1" | "dev_net" | "devnet" => Ok(Network::DevNet),
+            "2" | "main_net" | "mainnet" | "mainnet-beta" => Ok(Network::MainNet),
+            _ => Err(Error::InvalidConfig(format!("unknown network {}", s)))
+        }
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct SubmitProgramMeta {
     // the amount of supporter tokens to be sent to the user
@@ -61,19 +144,330 @@ pub struct VerifyProgramMeta {
     pub directory : String
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+// the program stores repo, commit and directory together in code_meta, separated by these markers.  Records
+// written by older versions of the program also wrap the repo in its own pair of markers
+pub const CODE_META_SIZE : usize = 512;
+pub const GIT_REPO_BEGIN : &str = "=======BEGIN GIT REPO=======\0";
+pub const GIT_REPO_END : &str = "=======END GIT REPO=======\0";
+pub const GIT_COMMIT_BEGIN : &str = "=======BEGIN GIT COMMIT=======\0";
+pub const GIT_DIR_BEGIN : &str = "=======BEGIN GIT DIR=======\0";
+pub const GIT_DIR_END : &str = "=======END GIT DIR=======\0";
+
+// test address, last verified slot, verified code, data hash, code meta and bump
+pub const METADATA_ACCOUNT_SIZE : usize = 32 + 8 + 1 + 32 + CODE_META_SIZE + 1;
+// records written before the bump seed was stored, which keep this size until the program is submitted again
+pub const LEGACY_METADATA_ACCOUNT_SIZE : usize = METADATA_ACCOUNT_SIZE - 1;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ProgramMetaData {
     pub test_address : Pubkey,
     pub last_verified_slot : u64,
     pub verified_code : u8,
     pub data_hash : [u8 ; 32],
-    pub code_meta : [u8 ; 512]
+    pub code_meta : [u8 ; CODE_META_SIZE],
+    // None for legacy records
+    pub bump : Option<u8>
+}
+
+/// The source a program was verified from
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct CodeMeta {
+    pub git_repo : String,
+    pub git_commit : String,
+    pub directory : String
+}
+
+impl ProgramMetaData {
+
+    /// Reads a record account, of either the current or the legacy size
+    pub fn unpack(data : &[u8]) -> Result<Self> {
+        let bump = match data.len() {
+            METADATA_ACCOUNT_SIZE => Some(data[LEGACY_METADATA_ACCOUNT_SIZE]),
+            LEGACY_METADATA_ACCOUNT_SIZE => None,
+            size => return Err(Error::InvalidRecordData(size))
+        };
+
+        let mut slot_bytes = [0u8; 8];
+        slot_bytes.copy_from_slice(&data[32..40]);
+        let mut data_hash = [0u8; 32];
+        data_hash.copy_from_slice(&data[41..73]);
+        let mut code_meta = [0u8; CODE_META_SIZE];
+        code_meta.copy_from_slice(&data[73..73 + CODE_META_SIZE]);
+
+        Ok(ProgramMetaData {
+            test_address : Pubkey::new(&data[..32]),
+            last_verified_slot : u64::from_le_bytes(slot_bytes),
+            verified_code : data[40],
+            data_hash : data_hash,
+            code_meta : code_meta,
+            bump : bump
+        })
+    }
+
+    /// the repo, commit and directory from code_meta, None if they haven't been written
+    pub fn source(&self) -> Option<CodeMeta> {
+
+        let find = |data : &[u8], marker : &str| twoway::find_bytes(data, marker.as_bytes());
+        let text = |data : &[u8]| String::from_utf8_lossy(data).into_owned();
+
+        let commit_begin = find(&self.code_meta, GIT_COMMIT_BEGIN)?;
+        let commit_start = commit_begin + GIT_COMMIT_BEGIN.len();
+        let dir_begin = commit_start + find(&self.code_meta[commit_start..], GIT_DIR_BEGIN)?;
+        let dir_start = dir_begin + GIT_DIR_BEGIN.len();
+        let dir_end = dir_start + find(&self.code_meta[dir_start..], GIT_DIR_END)?;
+
+        let mut git_repo = &self.code_meta[..commit_begin];
+        if let Some(repo) = git_repo.strip_prefix(GIT_REPO_BEGIN.as_bytes()) {
+            git_repo = repo.strip_suffix(GIT_REPO_END.as_bytes()).unwrap_or(repo);
+        }
+
+        Some(CodeMeta {
+            git_repo : text(git_repo),
+            git_commit : text(&self.code_meta[commit_start..dir_begin]),
+            directory : text(&self.code_meta[dir_start..dir_end])
+        })
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStage {
+    #[default]
+    Queued,
+    PreparingEnvironment,
+    Fetching,
+    Building,
+    Deploying,
+    Comparing,
+    Finalized
+}
+
+impl VerificationStage {
+    pub const ALL : [VerificationStage; 7] = [
+        VerificationStage::Queued,
+        VerificationStage::PreparingEnvironment,
+        VerificationStage::Fetching,
+        VerificationStage::Building,
+        VerificationStage::Deploying,
+        VerificationStage::Comparing,
+        VerificationStage::Finalized
+    ];
+
+    pub fn from_u8(value : u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VerificationStage::Queued => "queued",
+            VerificationStage::PreparingEnvironment => "preparing_environment",
+            VerificationStage::Fetching => "fetching",
+            VerificationStage::Building => "building",
+            VerificationStage::Deploying => "deploying",
+            VerificationStage::Comparing => "comparing",
+            VerificationStage::Finalized => "finalized"
+        }
+    }
+}
+
+impl std::str::FromStr for VerificationStage {
+    type Err = Error;
+
+    // accepts either the stage name or its index
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(index) = s.parse::<u8>() {
+            return Self::from_u8(index).ok_or_else(|| Error::InvalidConfig(format!("unknown stage {}", s)));
+        }
+
+        Self::ALL.iter().find(|stage| stage.name() == s).copied().ok_or_else(|| Error::InvalidConfig(format!("unknown stage {}", s)))
+    }
+}
+
+// mirrors the layout written by the program: a header holding the current status code, the current stage,
+// the index of the next entry, the number of entries and the bump seed, followed by a ring buffer of status entries
+pub const MAX_LOG_MESSAGE_LEN : usize = 255;
+pub const STATUS_HISTORY_LEN : usize = 8;
+
+pub const USER_HEADER_SIZE : usize = 5;
+pub const STATUS_ENTRY_SIZE : usize = 12 + MAX_LOG_MESSAGE_LEN;
+
+pub const USER_ACCOUNT_SIZE : usize = USER_HEADER_SIZE + STATUS_HISTORY_LEN * STATUS_ENTRY_SIZE;
+// user accounts written before the status history was kept hold the status code, the length of the message as a byte
+// and the message itself, and keep this size until the user submits again
+pub const LEGACY_USER_ACCOUNT_SIZE : usize = 2 + 4 * MAX_LOG_MESSAGE_LEN;
+
+pub const USER_STAGE_OFFSET : usize = 1;
+pub const USER_BUMP_OFFSET : usize = 4;
+pub const NO_PROGRESS : u8 = u8::MAX;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct StatusEntry {
+    pub status_code : u8,
+    pub stage : VerificationStage,
+    pub progress : Option<u8>,
+    pub timestamp : i64,
+    pub log_message : String
+}
+
+impl StatusEntry {
+    pub fn unpack(data: &[u8]) -> Result<Self> {
+        if data.len() < STATUS_ENTRY_SIZE {
+            return Err(Error::InvalidUserData);
+        }
+
+        let stage = VerificationStage::from_u8(data[1]).ok_or(Error::InvalidUserData)?;
+        let progress = if data[2] == NO_PROGRESS { None } else { Some(data[2]) };
+
+        let mut timestamp_bytes = [0u8; 8];
+        timestamp_bytes.copy_from_slice(&data[3..11]);
+        let message_len = data[11] as usize;
+
+        let log_message = std::str::from_utf8(&data[12..12 + message_len]).map_err(|_| Error::InvalidUserData)?;
+
+        Ok(StatusEntry {status_code : data[0], stage : stage, progress : progress, timestamp : i64::from_le_bytes(timestamp_bytes), log_message : log_message.to_string()})
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UserMetaData {
+    pub status_code : u8,
+    pub stage : VerificationStage,
+    // None for legacy user accounts
+    pub bump : Option<u8>,
+    // oldest entry first
+    pub history : Vec<StatusEntry>
+}
+
+impl UserMetaData {
+    /// Reads a user account, of either the current or the legacy size
+    pub fn unpack(data: &[u8]) -> Result<Self> {
+        if data.len() == LEGACY_USER_ACCOUNT_SIZE {
+            return Self::unpack_legacy(data);
+        }
+        if data.len() < USER_ACCOUNT_SIZE {
+            return Err(Error::InvalidUserData);
+        }
+
+        let stage = VerificationStage::from_u8(data[USER_STAGE_OFFSET]).ok_or(Error::InvalidUserData)?;
+        let next_index = data[2] as usize;
+        let count = data[3] as usize;
+
+        if next_index >= STATUS_HISTORY_LEN || count > STATUS_HISTORY_LEN {
+            return Err(Error::InvalidUserData);
+        }
+
+        let mut history = Vec::with_capacity(count);
+        for i in 0..count {
+            let index = (next_index + STATUS_HISTORY_LEN - count + i) % STATUS_HISTORY_LEN;
+            let start = USER_HEADER_SIZE + index * STATUS_ENTRY_SIZE;
+            history.push(StatusEntry::unpack(&data[start..start + STATUS_ENTRY_SIZE])?);
+        }
+
+        Ok(UserMetaData {status_code : data[0], stage : stage, bump : Some(data[USER_BUMP_OFFSET]), history : history})
+    }
+
+    // a legacy account only holds the latest message, which has no stage or timestamp, so it becomes a single entry
+    // queued at time zero
+    fn unpack_legacy(data: &[u8]) -> Result<Self> {
+        let message_len = data[1] as usize;
+
+        // the length was the message's byte count cast to a u8, so a long message can have been cut mid character
+        let log_message = String::from_utf8_lossy(&data[2..2 + message_len]);
+
+        let history = if message_len == 0 && data[0] == 0 {
+            Vec::new()
+        }
+        else {
+            vec![StatusEntry {status_code : data[0], log_message : log_message.to_string(), ..StatusEntry::default()}]
+        };
+
+        Ok(UserMetaData {status_code : data[0], stage : VerificationStage::default(), bump : None, history : history})
+    }
+
+    pub fn latest(&self) -> Option<&StatusEntry> {
+        self.history.last()
+    }
+}
+
+// verified codes stored in the program's meta data, these match python/codes.py
+pub const UNINITIALISED : u8 = 0;
+pub const MISMATCH : u8 = 1;
+pub const MATCH_BUT_UPGRADEABLE : u8 = 2;
+pub const MATCH : u8 = 3;
+
+pub fn verified_code_description(verified_code : u8) -> &'static str
+{
+    match verified_code {
+        UNINITIALISED => "not yet verified",
+        MISMATCH => "does not match the source",
+        MATCH_BUT_UPGRADEABLE => "matches the source but can still be upgraded",
+        MATCH => "matches the source and is immutable",
+        _ => "unknown verified code"
+    }
+}
+
+// status codes sent to the user account, these match python/codes.py
+pub const STATUS_IN_PROGRESS : u8 = 0;
+pub const STATUS_FINISHED : u8 = 1;
+
+pub const PROGRAM_DOESNT_EXIST : u8 = 100;
+pub const GIT_REPO_DOESNT_EXIST : u8 = 101;
+pub const GIT_DIR_DOESNT_EXIST : u8 = 102;
+pub const GIT_COMMIT_DOESNT_EXIST : u8 = 103;
+pub const UNSUPPORTED_LANGUAGE : u8 = 104;
+pub const BUILD_FAILED : u8 = 105;
+pub const DOCKER_DOESNT_EXIST : u8 = 106;
+pub const DOCKER_BUILD_FAILED : u8 = 107;
+pub const BAD_MAKEFILE : u8 = 108;
+pub const PREVIOUSLY_VERIFIED_IMMUTABLE : u8 = 109;
+pub const PREVIOUSLY_VERIFIED_MUTABLE : u8 = 110;
+pub const EXTRACT_FAILED : u8 = 111;
+pub const SECURITY_TXT_MISMATCH : u8 = 112;
+pub const SOURCE_REVISION_MISMATCH : u8 = 113;
+
+pub fn status_code_description(status_code : u8) -> &'static str
+{
+    match status_code {
+        STATUS_IN_PROGRESS => "verification in progress",
+        STATUS_FINISHED => "verification finished",
+        PROGRAM_DOESNT_EXIST => "program does not exist",
+        GIT_REPO_DOESNT_EXIST => "git repo does not exist",
+        GIT_DIR_DOESNT_EXIST => "directory does not exist in repo",
+        GIT_COMMIT_DOESNT_EXIST => "commit does not exist in repo",
+        UNSUPPORTED_LANGUAGE => "unsupported language",
+        BUILD_FAILED => "build failed",
+        DOCKER_DOESNT_EXIST => "unknown docker version",
+        DOCKER_BUILD_FAILED => "docker build failed",
+        BAD_MAKEFILE => "makefile does not define OUT_DIR",
+        PREVIOUSLY_VERIFIED_IMMUTABLE => "previously verified and immutable, new verification failed",
+        PREVIOUSLY_VERIFIED_MUTABLE => "previously verified and not upgraded since, new verification failed",
+        EXTRACT_FAILED => "archive failed to extract",
+        SECURITY_TXT_MISMATCH => "source code differs from security.txt",
+        SOURCE_REVISION_MISMATCH => "commit differs from the source revision in security.txt",
+        _ => "unknown status code"
+    }
+}
+
+// the custom errors the program returns, in the order of DaoPlaysError in program/src/error.rs
+pub const PROGRAM_ERRORS : [(&str, &str); 5] = [
+    ("InvalidInstruction", "invalid instruction"),
+    ("InvalidUserData", "user account data doesn't fit the expected layout"),
+    ("InvalidStage", "unknown verification stage"),
+    ("InvalidProgress", "progress is a percentage so can't be more than 100"),
+    ("CodeMetaTooLong", "the repo, commit and directory don't fit in the program meta data")
+];
+
+/// The name and description of a custom error returned by the program
+pub fn program_error(code : u32) -> Option<(&'static str, &'static str)> {
+    PROGRAM_ERRORS.get(code as usize).copied()
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct StatusMeta {
     pub user_pubkey : Pubkey,
     pub status_code : u8,
+    pub stage : VerificationStage,
+    pub progress : Option<u8>,
     pub log_message : String
 }
 
@@ -91,8 +485,173 @@ pub enum VerifyInstruction {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ProgramJsonData {
-    pub upgradeable: bool,
-    pub source_code: String
-}
\ No newline at end of file
+// an UpdateStatus instruction as sent before status updates carried a stage and progress
+#[derive(BorshDeserialize)]
+struct LegacyStatusMeta {
+    user_pubkey : Pubkey,
+    status_code : u8,
+    log_message : String
+}
+
+const UPDATE_STATUS_TAG : u8 = 2;
+
+impl VerifyInstruction {
+    /// Decodes an instruction in either the current layout or, for UpdateStatus, the layout without a stage and progress
+    pub fn unpack(data : &[u8]) -> Result<Self> {
+        match VerifyInstruction::try_from_slice(data) {
+            Ok(instruction) => Ok(instruction),
+            Err(err) => match data.split_first() {
+                Some((&UPDATE_STATUS_TAG, rest)) => {
+                    let legacy = LegacyStatusMeta::try_from_slice(rest).map_err(|_| Error::SerializationError(err))?;
+                    Ok(VerifyInstruction::UpdateStatus {metadata : StatusMeta {
+                        user_pubkey : legacy.user_pubkey,
+                        status_code : legacy.status_code,
+                        stage : VerificationStage::default(),
+                        progress : None,
+                        log_message : legacy.log_message
+                    }})
+                },
+                _ => Err(Error::SerializationError(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a status entry as the program writes it, with the timestamp standing in for its position
+    fn entry(timestamp : i64) -> Vec<u8> {
+        let message = format!("entry {}", timestamp);
+        let mut data = vec![STATUS_IN_PROGRESS, VerificationStage::Building as u8, 50];
+        data.extend(timestamp.to_le_bytes());
+        data.push(message.len() as u8);
+        data.extend(message.as_bytes());
+        data.resize(STATUS_ENTRY_SIZE, 0);
+        data
+    }
+
+    fn user_account(next_index : u8, count : u8) -> Vec<u8> {
+        let mut data = vec![STATUS_FINISHED, VerificationStage::Finalized as u8, next_index, count, 254];
+        for index in 0..STATUS_HISTORY_LEN {
+            data.extend(entry(index as i64));
+        }
+        data
+    }
+
+    fn timestamps(user : &UserMetaData) -> Vec<i64> {
+        user.history.iter().map(|entry| entry.timestamp).collect()
+    }
+
+    #[test]
+    fn history_is_read_oldest_first() {
+        let user = UserMetaData::unpack(&user_account(3, 3)).unwrap();
+        assert_eq!(timestamps(&user), vec![0, 1, 2]);
+        assert_eq!((user.status_code, user.stage, user.bump), (STATUS_FINISHED, VerificationStage::Finalized, Some(254)));
+
+        let latest = user.latest().unwrap();
+        assert_eq!((latest.stage, latest.progress, latest.log_message.as_str()), (VerificationStage::Building, Some(50), "entry 2"));
+
+        assert!(UserMetaData::unpack(&user_account(0, 0)).unwrap().history.is_empty());
+    }
+
+    #[test]
+    fn history_wraps_around_the_ring() {
+        assert_eq!(timestamps(&UserMetaData::unpack(&user_account(1, 3)).unwrap()), vec![6, 7, 0]);
+        assert_eq!(timestamps(&UserMetaData::unpack(&user_account(3, 8)).unwrap()), vec![3, 4, 5, 6, 7, 0, 1, 2]);
+        assert_eq!(timestamps(&UserMetaData::unpack(&user_account(0, 8)).unwrap()), (0..8).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn invalid_user_accounts_are_rejected() {
+        assert!(UserMetaData::unpack(&user_account(8, 1)).is_err());
+        assert!(UserMetaData::unpack(&user_account(0, 9)).is_err());
+        assert!(UserMetaData::unpack(&user_account(1, 1)[..USER_ACCOUNT_SIZE - 1]).is_err());
+
+        let mut bad_stage = user_account(1, 1);
+        bad_stage[USER_STAGE_OFFSET] = VerificationStage::ALL.len() as u8;
+        assert!(UserMetaData::unpack(&bad_stage).is_err());
+    }
+
+    // written as the program did before the status history, with the end of an earlier, longer message left behind
+    fn legacy_user_account(status_code : u8, message : &str) -> Vec<u8> {
+        let mut data = vec![0u8; LEGACY_USER_ACCOUNT_SIZE];
+        data[2..2 + 40].copy_from_slice(&[b'x'; 40]);
+        data[0] = status_code;
+        data[1] = message.len() as u8;
+        data[2..2 + message.len()].copy_from_slice(message.as_bytes());
+        data
+    }
+
+    #[test]
+    fn legacy_user_accounts_hold_one_entry() {
+        let message = "git repo does not exist";
+        let user = UserMetaData::unpack(&legacy_user_account(GIT_REPO_DOESNT_EXIST, message)).unwrap();
+        assert_eq!(user.bump, None);
+        assert_eq!(user.history.len(), 1);
+        assert_eq!((user.history[0].status_code, user.history[0].log_message.as_str()), (GIT_REPO_DOESNT_EXIST, message));
+
+        // 257 bytes, which the program recorded as a length of 1, half of the first character
+        let long_message = format!("{}a", "é".repeat(128));
+        let user = UserMetaData::unpack(&legacy_user_account(STATUS_IN_PROGRESS, &long_message)).unwrap();
+        assert_eq!(user.history[0].log_message, "\u{fffd}");
+
+        assert!(UserMetaData::unpack(&vec![0; LEGACY_USER_ACCOUNT_SIZE]).unwrap().history.is_empty());
+    }
+
+    #[test]
+    fn records_of_both_sizes_are_read() {
+        let mut data = vec![0u8; METADATA_ACCOUNT_SIZE];
+        data[32..40].copy_from_slice(&1234u64.to_le_bytes());
+        data[40] = MATCH;
+        data[41] = 0xab;
+        data[LEGACY_METADATA_ACCOUNT_SIZE] = 253;
+
+        let record = ProgramMetaData::unpack(&data).unwrap();
+        assert_eq!((record.last_verified_slot, record.verified_code, record.data_hash[0], record.bump), (1234, MATCH, 0xab, Some(253)));
+
+        let legacy = ProgramMetaData::unpack(&data[..LEGACY_METADATA_ACCOUNT_SIZE]).unwrap();
+        assert_eq!(legacy, ProgramMetaData {bump : None, ..record});
+
+        assert!(matches!(ProgramMetaData::unpack(&data[..LEGACY_METADATA_ACCOUNT_SIZE - 1]), Err(Error::InvalidRecordData(584))));
+        assert!(matches!(ProgramMetaData::unpack(&[0; METADATA_ACCOUNT_SIZE + 1]), Err(Error::InvalidRecordData(587))));
+    }
+
+    #[test]
+    fn legacy_status_updates_are_decoded() {
+        let user = Pubkey::new_unique();
+        let message = "building";
+
+        let mut data = vec![UPDATE_STATUS_TAG];
+        data.extend(user.to_bytes());
+        data.push(STATUS_IN_PROGRESS);
+        data.extend((message.len() as u32).to_le_bytes());
+        data.extend(message.as_bytes());
+
+        let expected = VerifyInstruction::UpdateStatus {metadata : StatusMeta {
+            user_pubkey : user,
+            status_code : STATUS_IN_PROGRESS,
+            stage : VerificationStage::Queued,
+            progress : None,
+            log_message : message.to_string()
+        }};
+        assert_eq!(VerifyInstruction::unpack(&data).unwrap(), expected);
+    }
+
+    #[test]
+    fn current_instructions_are_decoded() {
+        let instruction = VerifyInstruction::UpdateStatus {metadata : StatusMeta {
+            user_pubkey : Pubkey::new_unique(),
+            status_code : STATUS_IN_PROGRESS,
+            stage : VerificationStage::Deploying,
+            progress : Some(75),
+            log_message : "deploying".to_string()
+        }};
+        assert_eq!(VerifyInstruction::unpack(&instruction.try_to_vec().unwrap()).unwrap(), instruction);
+
+        assert!(VerifyInstruction::unpack(&[]).is_err());
+        assert!(VerifyInstruction::unpack(&[UPDATE_STATUS_TAG, 1, 2, 3]).is_err());
+        assert!(VerifyInstruction::unpack(&[9]).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::elf::Elf;
+use crate::manifest::SubmitManifest;
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// crates whose version is worth reporting, found in the source paths that panic messages leave in the program
+const TRACKED_CRATES : [&str; 4] = ["solana-program", "anchor-lang", "spl-token", "borsh"];
+
+// the solana build toolchains are installed to paths like ~/.cache/solana/v1.27/bpf-tools, older ones
+// being bpf-tools and newer ones platform-tools
+const TOOLS_MARKERS : [&str; 3] = ["/platform-tools", "/bpf-tools", "/sbf-tools"];
+
+// shortest run of printable bytes treated as a string when scanning the program
+const MIN_STRING_LEN : usize = 8;
+
+/// What a program's ELF says about the toolchain that built it
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ToolchainInfo {
+    // the entries of the .comment section, e.g. "rustc version 1.59.0-dev" or "Linker: LLD 13.0.0"
+    pub comments : Vec<String>,
+    pub rustc_version : Option<String>,
+    pub llvm_version : Option<String>,
+    pub tools_version : Option<String>,
+    pub crate_versions : BTreeMap<String, String>
+}
+
+// the version number at the start of text, e.g. 1.10.39 from "1.10.39/src/lib.rs"
+fn leading_version(text : &str) -> Option<String> {
+    let end = text.find(|c : char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let version = text[..end].trim_end_matches('.');
+    if version.contains('.') && version.starts_with(|c : char| c.is_ascii_digit()) {
+        return Some(version.to_string());
+    }
+    None
+}
+
+// the version following the first occurrence of marker in text
+fn version_after(text : &str, marker : &str) -> Option<String> {
+    let start = text.find(marker)? + marker.len();
+    leading_version(text[start..].trim_start_matches('v'))
+}
+
+fn printable_strings(data : &[u8]) -> Vec<String> {
+    data.split(|b| !(0x20..0x7f).contains(b))
+        .filter(|s| s.len() >= MIN_STRING_LEN)
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
+impl ToolchainInfo {
+
+    pub fn extract(data : &[u8]) -> ToolchainInfo {
+
+        let mut info = ToolchainInfo::default();
+
+        if let Some(comment) = Elf::parse(data).and_then(|elf| elf.section(".comment").cloned()) {
+            info.comments = comment.data(data).split(|b| *b == 0)
+                .filter(|s| !s.is_empty())
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+                .collect();
+        }
+
+        for comment in &info.comments {
+            if info.rustc_version.is_none() {
+                info.rustc_version = version_after(comment, "rustc version ");
+            }
+            if info.llvm_version.is_none() {
+                info.llvm_version = version_after(comment, "LLD ")
+                    .or_else(|| version_after(comment, "clang version "))
+                    .or_else(|| version_after(comment, "LLVM version "));
+            }
+        }
+
+        // source paths look like .../registry/src/github.com-1ecc6299db9ec823/solana-program-1.10.39/src/...
+        for string in printable_strings(data) {
+            for name in TRACKED_CRATES {
+                if info.crate_versions.contains_key(name) {
+                    continue;
+                }
+                if let Some(version) = version_after(&string, &format!("/{}-", name)) {
+                    info.crate_versions.insert(name.to_string(), version);
+                }
+            }
+
+            if info.tools_version.is_none() {
+                info.tools_version = TOOLS_MARKERS.iter().find_map(|marker| {
+                    let install_dir = &string[..string.find(marker)?];
+                    let version = leading_version(install_dir.rsplit('/').next()?.strip_prefix('v')?)?;
+                    Some(format!("{} v{}", &marker[1..], version))
+                });
+            }
+        }
+
+        info
+    }
+
+    /// toolchain fields for a submission, for anything that could be worked out.  The rust version is left out: it picks
+    /// the rust image the build runs in, while the rustc in the program is the one bundled with the build tools, and
+    /// nothing in the program says which host toolchain was used
+    pub fn suggested_manifest(&self) -> SubmitManifest {
+        SubmitManifest {
+            solana_version : self.crate_versions.get("solana-program").cloned(),
+            anchor_version : self.crate_versions.get("anchor-lang").cloned(),
+            ..SubmitManifest::default()
+        }
+    }
+
+    pub fn print(&self) {
+
+        let unknown = || "unknown".to_string();
+
+        for comment in &self.comments {
+            println!("comment: {}", comment);
+        }
+        println!("rustc: {}", self.rustc_version.clone().unwrap_or_else(unknown));
+        println!("llvm: {}", self.llvm_version.clone().unwrap_or_else(unknown));
+        println!("build tools: {}", self.tools_version.clone().unwrap_or_else(unknown));
+        for (name, version) in &self.crate_versions {
+            println!("{}: {}", name, version);
+        }
+    }
+}
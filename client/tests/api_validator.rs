@@ -0,0 +1,128 @@
+// Runs the explorer API against solana-test-validator, with the program loaded from a local build.  The test is ignored by default as
+// it needs solana-test-validator on the PATH and a build of the program: run `cargo build-bpf` in program/, then
+// `cargo test --test api_validator -- --ignored` here.  SOL_VERIFY_PROGRAM_SO overrides where the build is read from
+
+// struct initialisers name every field, as in `address : address`, throughout the client
+#![allow(clippy::redundant_field_names)]
+
+use sol_verify_client::api::{ApiServer, ApiBackend};
+use sol_verify_client::client::{VerifierClient, ClusterConfig};
+use sol_verify_client::manifest::SubmitManifest;
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+const RPC_PORT : u16 = 18999;
+const FAUCET_PORT : u16 = 19900;
+const GIT_REPO : &str = "https://github.com/daoplays/sol_verify";
+
+// the validator and its ledger, which are cleaned up however the test ends
+struct Validator {
+    process : Child,
+    ledger : PathBuf
+}
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_dir_all(&self.ledger);
+    }
+}
+
+fn start_validator(program_id : &Pubkey) -> Validator {
+    let program_so = std::env::var("SOL_VERIFY_PROGRAM_SO")
+        .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/../program/target/deploy/solana_verify_v05.so").to_string());
+    assert!(std::path::Path::new(&program_so).exists(), "{} doesn't exist, build the program with cargo build-bpf", program_so);
+
+    let ledger = std::env::temp_dir().join(format!("sol_verify_api_validator_{}", std::process::id()));
+    let process = Command::new("solana-test-validator")
+        .arg("--reset")
+        .arg("--quiet")
+        .arg("--ledger").arg(&ledger)
+        .arg("--rpc-port").arg(RPC_PORT.to_string())
+        .arg("--faucet-port").arg(FAUCET_PORT.to_string())
+        .arg("--bpf-program").arg(program_id.to_string()).arg(&program_so)
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("solana-test-validator has to be on the PATH");
+
+    Validator {process : process, ledger : ledger}
+}
+
+fn verifier_client(url : &str, program_id : Pubkey) -> VerifierClient {
+    let clusters = ClusterConfig {test_net : url.to_string(), dev_net : url.to_string(), main_net : url.to_string()};
+    VerifierClient::new(RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()), program_id, clusters)
+}
+
+#[tokio::test]
+#[ignore]
+async fn api_serves_a_submitted_program() {
+    let program_id = Keypair::new().pubkey();
+    let _validator = start_validator(&program_id);
+
+    let url = format!("http://127.0.0.1:{}", RPC_PORT);
+    let rpc = RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed());
+
+    let mut healthy = false;
+    for _ in 0..120 {
+        if rpc.get_health().await.is_ok() {
+            healthy = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    assert!(healthy, "solana-test-validator didn't start");
+
+    let payer = Keypair::new();
+    rpc.request_airdrop(&payer.pubkey(), LAMPORTS_PER_SOL).await.unwrap();
+    let mut funded = false;
+    for _ in 0..120 {
+        if rpc.get_balance(&payer.pubkey()).await.unwrap_or(0) > 0 {
+            funded = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    assert!(funded, "the airdrop didn't land");
+
+    let server = ApiServer::new(verifier_client(&url, program_id), ApiBackend::Chain, 50, Duration::from_secs(30));
+
+    // the verifier itself is the program submitted, as it's the one program the validator is sure to have
+    let unsubmitted = Keypair::new().pubkey();
+    assert_eq!(server.handle("GET", &format!("/programs/dev_net/{}", unsubmitted), None).await.status, 404);
+
+    let manifest = SubmitManifest {
+        address : Some(program_id.to_string()),
+        network : Some("dev_net".to_string()),
+        git_repo : Some(GIT_REPO.to_string()),
+        directory : Some("program".to_string()),
+        rust_version : Some("1.62".to_string()),
+        solana_version : Some("1.10.39".to_string()),
+        ..SubmitManifest::default()
+    };
+    verifier_client(&url, program_id).submit(&payer, manifest).await.unwrap();
+
+    let response = server.handle("GET", &format!("/programs/dev_net/{}", program_id), None).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+    let program : serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(program["record"]["git_repo"], GIT_REPO);
+    assert_eq!(program["upgradeable"], false);
+    assert_eq!(program["history"].as_array().unwrap().len(), 1);
+
+    let svg = server.handle("GET", &format!("/badges/dev_net/{}.svg", program_id), None).await;
+    assert_eq!((svg.status, svg.content_type), (200, "image/svg+xml"));
+    let shields = server.handle("GET", &format!("/badges/dev_net/{}.json", program_id), None).await;
+    assert_eq!((shields.status, shields.content_type), (200, "application/json"));
+
+    let response = server.handle("GET", "/repos", Some(&format!("url={}.git", GIT_REPO))).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+    let repo : serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(repo["records"].as_array().unwrap().len(), 1);
+}
@@ -0,0 +1,29 @@
+#!/usr/bin/env bash
+#
+# Builds the program as it was before the stored bump seeds and the zero copy account layouts into
+# tests/fixtures/solana_verify_v05_before.so, so that tests/compute_units.rs can compare against it.
+# The build accepts the same test verifier key as a build with the test-verifier feature
+#
+
+here="$(cd "$(dirname "$0")" && pwd)"
+
+# the last revision that derived every account with find_program_address and stored records with borsh
+revision=${1:-57d41cf}
+
+# the key in src/accounts.rs for builds with the test-verifier feature
+test_verifier_key=GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB
+
+set -e
+
+worktree=$(mktemp -d)
+trap 'git -C "$here" worktree remove --force "$worktree"' EXIT
+git -C "$here" worktree add --detach "$worktree" "$revision"
+
+sed -i "s/declare_id!(\"[1-9A-HJ-NP-Za-km-z]*\")/declare_id!(\"$test_verifier_key\")/" "$worktree"/program/src/accounts.rs
+
+out_dir=$(mktemp -d)
+(cd "$worktree"/program && cargo build-bpf --bpf-out-dir "$out_dir")
+
+mkdir -p "$here"/../tests/fixtures
+cp "$out_dir"/solana_verify_v05.so "$here"/../tests/fixtures/solana_verify_v05_before.so
+rm -rf "$out_dir"
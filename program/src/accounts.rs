@@ -1,11 +1,20 @@
 use solana_program::{pubkey::Pubkey, declare_id};
 // functions to calculate expected public keys
 
+#[cfg(not(feature = "test-verifier"))]
 mod daoplays {
     use super::*;
     declare_id!("7LtYL85tZPpYweZMqeHzX6DAaGsrY61DEtnwiPyJaVCD");   
 }
 
+// builds with the test-verifier feature accept the key generated from TEST_VERIFIER_SEED in tests/compute_units.rs
+// instead, so that the verifier's instructions can be benchmarked.  Never deploy such a build
+#[cfg(feature = "test-verifier")]
+mod daoplays {
+    use super::*;
+    declare_id!("GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB");
+}
+
 
 pub fn get_expected_daoplays_key() -> Pubkey
 {
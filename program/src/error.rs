@@ -6,7 +6,19 @@ use solana_program::program_error::ProgramError;
 pub enum DaoPlaysError {
     /// Invalid instruction
     #[error("Invalid Instruction")]
-    InvalidInstruction
+    InvalidInstruction,
+    /// User account data doesn't fit the expected layout
+    #[error("Invalid User Data")]
+    InvalidUserData,
+    /// Unknown verification stage
+    #[error("Invalid Stage")]
+    InvalidStage,
+    /// Progress is a percentage so can't be more than 100
+    #[error("Invalid Progress")]
+    InvalidProgress,
+    /// The repo, commit and directory don't fit in the program meta data
+    #[error("Code Meta Too Long")]
+    CodeMetaTooLong
 }
 
 impl From<DaoPlaysError> for ProgramError {
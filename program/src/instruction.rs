@@ -1,6 +1,6 @@
 use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 use borsh::{BorshDeserialize, BorshSerialize};
-use crate::error::DaoPlaysError::InvalidInstruction;
+use crate::error::DaoPlaysError::{InvalidInstruction, InvalidStage};
 
 
 pub fn network_to_string(network :  Network) ->  String 
@@ -47,10 +47,51 @@ pub struct VerifyProgramMeta {
     pub directory : String
 }
 
+// the stages of a verification run, in the order they happen.  These are stored as a single byte
+// in the user account so front ends can filter on them
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationStage {
+    Queued,
+    PreparingEnvironment,
+    Fetching,
+    Building,
+    Deploying,
+    Comparing,
+    Finalized
+}
+
+// #[default] on an enum variant needs a newer rustc than the bpf toolchain has
+#[allow(clippy::derivable_impls)]
+impl Default for VerificationStage {
+    fn default() -> Self {
+        VerificationStage::Queued
+    }
+}
+
+impl TryFrom<u8> for VerificationStage {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => VerificationStage::Queued,
+            1 => VerificationStage::PreparingEnvironment,
+            2 => VerificationStage::Fetching,
+            3 => VerificationStage::Building,
+            4 => VerificationStage::Deploying,
+            5 => VerificationStage::Comparing,
+            6 => VerificationStage::Finalized,
+            _ => return Err(InvalidStage.into()),
+        })
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct StatusMeta {
     pub user_pubkey : Pubkey,
     pub status_code : u8,
+    pub stage : VerificationStage,
+    // percentage complete, if the verifier can estimate it
+    pub progress : Option<u8>,
     pub log_message : String
 }
 
@@ -74,13 +115,13 @@ impl VerifyInstruction {
         let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
         Ok(match tag {
             0 => Self::SubmitProgram  {
-                metadata: SubmitProgramMeta::try_from_slice(&rest)?,
+                metadata: SubmitProgramMeta::try_from_slice(rest)?,
             },
             1 => Self::VerifyProgram  {
-                metadata: VerifyProgramMeta::try_from_slice(&rest)?,
+                metadata: VerifyProgramMeta::try_from_slice(rest)?,
             },
             2 => Self::UpdateStatus  {
-                metadata: StatusMeta::try_from_slice(&rest)?,
+                metadata: StatusMeta::try_from_slice(rest)?,
             },
             _ => return Err(InvalidInstruction.into()),
         })
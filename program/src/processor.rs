@@ -1,7 +1,6 @@
-use std::str;
 use crate::instruction;
-use crate::state::{ProgramMetaData};
-use borsh::{BorshDeserialize, BorshSerialize};
+use crate::state::{ProgramMetaData, UserMetaData, StatusEntry};
+use borsh::BorshDeserialize;
 use crate::accounts;
 use crate::utils;
 use crate::state;
@@ -11,17 +10,17 @@ use solana_program::{
     entrypoint::ProgramResult,
     program_error::ProgramError,
     msg,
-    pubkey::Pubkey
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar}
 };
 
-use crate::{instruction::{VerifyInstruction, SubmitProgramMeta, VerifyProgramMeta, StatusMeta}};
-use state::{GIT_REPO_BEGIN, GIT_REPO_END, GIT_COMMIT_BEGIN, GIT_COMMIT_END, GIT_DIR_BEGIN, GIT_DIR_END};
+use crate::{instruction::{VerifyInstruction, SubmitProgramMeta, VerifyProgramMeta, StatusMeta, VerificationStage}};
+use crate::error::DaoPlaysError;
 
 pub struct Processor;
 impl Processor {
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
-        msg!("process!");
-        let instruction = VerifyInstruction::try_from_slice(&instruction_data[..])?;
+        let instruction = VerifyInstruction::try_from_slice(instruction_data)?;
 
         match instruction {
             VerifyInstruction::SubmitProgram {metadata} => {
@@ -55,7 +54,6 @@ impl Processor {
 
         let system_program_account_info = next_account_info(account_info_iter)?;
 
-        msg!("in submit, check signer");
         // the first account should be the funding account and should be a signer
         if !program_owner_account_info.is_signer {
             msg!("expected first account as signer");
@@ -63,25 +61,22 @@ impl Processor {
         }
 
         let network_id : String = instruction::network_to_string(metadata.network);
-        let (expected_metadata_key, bump_seed) = Pubkey::find_program_address(&[&metadata.address.to_bytes(), &network_id.as_bytes()], &program_id);
-        
-        msg!("in submit, check program meta data");
-        if program_metadata_account_info.key != &expected_metadata_key
-        { 
-            msg!("expected second account to be the program metadata account {}", expected_metadata_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        let bump_seed = utils::check_program_data_account(
+            program_metadata_account_info,
+            program_id,
+            &metadata.address.to_bytes(),
+            network_id.as_bytes(),
+            state::METADATA_BUMP_OFFSET
+        )?;
 
-        let (expected_user_metadata_key, user_bump_seed) = Pubkey::find_program_address(&[&program_owner_account_info.key.to_bytes(), b"user_account"], &program_id);
+        let user_bump_seed = utils::check_program_data_account(
+            user_metadata_account_info,
+            program_id,
+            &program_owner_account_info.key.to_bytes(),
+            b"user_account",
+            state::USER_BUMP_OFFSET
+        )?;
 
-        msg!("in submit, check user meta data");
-        if user_metadata_account_info.key != &expected_user_metadata_key
-        { 
-            msg!("expected third account to be the user metadata account {}", expected_user_metadata_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
-        
-        msg!("in submit, check system program");
         // the third and final account is the system_program
         if system_program_account_info.key != &solana_program::system_program::id() {
             msg!("expected fourth account to be the system program {}", solana_program::system_program::id());
@@ -110,34 +105,34 @@ impl Processor {
             state::get_userdata_size()
         )?;
 
-        // reset the users state
-        0u8.serialize(&mut &mut user_metadata_account_info.data.borrow_mut()[..1])?;
+        // accounts created before the current layouts need to be grown
+        utils::resize_program_data_account(
+            program_owner_account_info,
+            program_metadata_account_info,
+            state::get_metadata_size()
+        )?;
 
+        utils::resize_program_data_account(
+            program_owner_account_info,
+            user_metadata_account_info,
+            state::get_userdata_size()
+        )?;
 
-        let log_array = ["Program", &metadata.address.to_string(), ": accounts created"];
-        let log_message = (log_array.join(" ")).to_string();
-        let log_message_bytes = log_message.as_bytes();
+        ProgramMetaData::load_mut(&mut program_metadata_account_info.data.borrow_mut()[..])?.bump = bump_seed;
 
-        let s = match str::from_utf8(log_message_bytes) {
-            Ok(v) => v,
-            Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
+        // reset the users state
+        let log_message = ["Program", &metadata.address.to_string(), ": accounts created"].join(" ");
+        let entry = StatusEntry {
+            status_code : 0,
+            stage : VerificationStage::Queued,
+            progress : Some(0),
+            timestamp : Clock::get()?.unix_timestamp,
+            log_message
         };
 
-        msg!("try string again {}", s);
-
-        let message_len = log_message_bytes.len() as u8;
-        let end_index = 2 + message_len as usize;
-        message_len.serialize(&mut &mut user_metadata_account_info.data.borrow_mut()[1..2])?;
-
-        msg!("serialize bytes size {} end {} max {}", message_len, end_index, state::get_userdata_size());
-        for i  in 0..message_len {
-            let index = i as usize;
-            let start = 2 + i as usize;
-            let end = start + 1 as usize;
-            log_message_bytes[index].serialize(&mut &mut user_metadata_account_info.data.borrow_mut()[start..end])?;
-
-        }
-
+        let mut user_data = user_metadata_account_info.data.borrow_mut();
+        UserMetaData::reset(&mut user_data[..], user_bump_seed)?;
+        UserMetaData::push(&mut user_data[..], &entry)?;
 
         Ok(())
 
@@ -172,14 +167,14 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let (expected_user_metadata_key, _user_bump_seed) = Pubkey::find_program_address(&[&metadata.user_pubkey.to_bytes(), b"user_account"], &program_id);
-        
-        if user_metadata_account_info.key != &expected_user_metadata_key
-        { 
-            msg!("expected second account to be the user metadata account {}", expected_user_metadata_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
-        
+        utils::check_program_data_account(
+            user_metadata_account_info,
+            program_id,
+            &metadata.user_pubkey.to_bytes(),
+            b"user_account",
+            state::USER_BUMP_OFFSET
+        )?;
+
         // check that the user id account exists
         if **user_metadata_account_info.try_borrow_lamports()? == 0 {
             msg!("user's meta data account doesn't exist");
@@ -192,21 +187,22 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData);
         }
         
-        metadata.status_code.serialize(&mut &mut user_metadata_account_info.data.borrow_mut()[0..1])?;
-        let log_message_bytes = metadata.log_message.as_bytes();
-
-        let message_len = log_message_bytes.len() as u8;
-        message_len.serialize(&mut &mut user_metadata_account_info.data.borrow_mut()[1..2])?;
-
-        msg!("serialize bytes size {}", message_len);
-        for i  in 0..message_len {
-            let index = i as usize;
-            let start = 2 + i as usize;
-            let end = start + 1 as usize;
-            log_message_bytes[index].serialize(&mut &mut user_metadata_account_info.data.borrow_mut()[start..end])?;
-
+        if let Some(progress) = metadata.progress {
+            if progress > 100 {
+                msg!("progress {} is not a percentage", progress);
+                return Err(DaoPlaysError::InvalidProgress.into());
+            }
         }
 
+        let entry = StatusEntry {
+            status_code : metadata.status_code,
+            stage : metadata.stage,
+            progress : metadata.progress,
+            timestamp : Clock::get()?.unix_timestamp,
+            log_message : metadata.log_message
+        };
+        UserMetaData::push(&mut user_metadata_account_info.data.borrow_mut()[..], &entry)?;
+
         Ok(())
 
     }
@@ -249,45 +245,23 @@ impl Processor {
         }
 
         let network_id : String = instruction::network_to_string(metadata.network);
-        let (expected_metadata_key, _bump_seed) = Pubkey::find_program_address(&[&metadata.real_address.to_bytes(), &network_id.as_bytes()], &program_id);
-        
-        
-        if program_metadata_account_info.key != &expected_metadata_key
-        { 
-            msg!("expected second account to be the program metadata account {}", expected_metadata_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        utils::check_program_data_account(
+            program_metadata_account_info,
+            program_id,
+            &metadata.real_address.to_bytes(),
+            network_id.as_bytes(),
+            state::METADATA_BUMP_OFFSET
+        )?;
 
 
-        let mut current_state = ProgramMetaData::try_from_slice(&program_metadata_account_info.data.borrow()[..])?;
+        let mut program_metadata = program_metadata_account_info.data.borrow_mut();
+        let current_state = ProgramMetaData::load_mut(&mut program_metadata[..])?;
 
         current_state.verified_code = metadata.verified_code;
         current_state.test_address = metadata.test_address;
         current_state.last_verified_slot = metadata.verified_slot;
         current_state.data_hash = metadata.data_hash;
-
-        // serialise the code meta data
-        let mut code_string : String = metadata.git_repo.to_owned();
-
-        code_string += GIT_COMMIT_BEGIN;
-        code_string += &metadata.git_commit;
-
-        code_string += GIT_DIR_BEGIN;
-        code_string += &metadata.directory;
-        code_string += GIT_DIR_END;
-
-        let mut code_meta : [u8; 512] = [0 ; 512];
-
-        let code_string_bytes = code_string.as_bytes();
-        for i in 0..code_string_bytes.len() {
-            code_meta[i] = code_string_bytes[i];
-        }
-
-        current_state.code_meta = code_meta;
-        msg!("current_state {:?}", current_state);
-        
-        current_state.serialize(&mut &mut program_metadata_account_info.data.borrow_mut()[..])?;
-
+        current_state.set_code_meta(&metadata.git_repo, &metadata.git_commit, &metadata.directory)?;
 
         Ok(())
 
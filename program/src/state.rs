@@ -1,7 +1,9 @@
-use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
-    pubkey::Pubkey,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
 };
+use crate::error::DaoPlaysError::{InvalidUserData, CodeMetaTooLong};
+use crate::instruction::VerificationStage;
 use solana_security_txt::security_txt;
 
 pub const GIT_REPO_BEGIN: &str = "=======BEGIN GIT REPO=======\0";
@@ -13,43 +15,273 @@ pub const GIT_COMMIT_END: &str = "=======END GIT COMMIT=======\0";
 pub const GIT_DIR_BEGIN: &str = "=======BEGIN GIT DIR=======\0";
 pub const GIT_DIR_END: &str = "=======END GIT DIR=======\0";
 
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub struct ProgramMetaData {
-    pub test_address : Pubkey,
-    pub last_verified_slot : u64,
-    pub verified_code : u8,
-    pub data_hash : [u8 ; 32],
-    pub code_meta : [u8 ; 512],
+// the zero copy account layouts.  The bytemuck_derive releases that build with the bpf toolchain's rustc generate a
+// padding check the compiler reports as dead code, so that is allowed for these structs alone
+#[allow(dead_code)]
+mod layout {
+    use bytemuck::{Pod, Zeroable};
+    use solana_program::pubkey::Pubkey;
+    use super::{MAX_LOG_MESSAGE_LEN, STATUS_HISTORY_LEN};
+
+    // the program meta data account is read and written in place, so the struct matches the account layout exactly
+    #[repr(C, packed)]
+    #[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+    pub struct ProgramMetaData {
+        pub test_address : Pubkey,
+        pub last_verified_slot : u64,
+        pub verified_code : u8,
+        // sha256 of the program ELF without the zero padding of the program data account
+        pub data_hash : [u8 ; 32],
+        pub code_meta : [u8 ; 512],
+        // kept last so the offsets of the other fields are unchanged
+        pub bump : u8
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    pub struct UserHeader {
+        pub status_code : u8,
+        pub stage : u8,
+        pub next_index : u8,
+        pub count : u8,
+        pub bump : u8
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    pub struct StatusEntryData {
+        pub status_code : u8,
+        pub stage : u8,
+        pub progress : u8,
+        pub timestamp : [u8; 8],
+        pub message_len : u8,
+        pub message : [u8; MAX_LOG_MESSAGE_LEN]
+    }
+
+    // zero copy view of the whole user account
+    #[repr(C)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    pub struct UserAccount {
+        pub header : UserHeader,
+        pub entries : [StatusEntryData; STATUS_HISTORY_LEN]
+    }
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Default)]
-pub struct UserMetaData {
+pub use layout::{ProgramMetaData, UserHeader, StatusEntryData, UserAccount};
+
+// the bump seed is the last byte of the program meta data account
+pub const METADATA_BUMP_OFFSET : usize = 585;
+
+impl ProgramMetaData {
+    /// A read only view of a program meta data account
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        data.get(..get_metadata_size())
+            .and_then(|bytes| bytemuck::try_from_bytes(bytes).ok())
+            .ok_or(ProgramError::InvalidAccountData)
+    }
+
+    /// A mutable view of a program meta data account, so fields can be written directly
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        data.get_mut(..get_metadata_size())
+            .and_then(|bytes| bytemuck::try_from_bytes_mut(bytes).ok())
+            .ok_or(ProgramError::InvalidAccountData)
+    }
+
+    /// Writes the repo, commit and directory into code_meta, separated by the sentinel strings
+    pub fn set_code_meta(&mut self, git_repo : &str, git_commit : &str, directory : &str) -> ProgramResult {
+        let parts = [git_repo, GIT_COMMIT_BEGIN, git_commit, GIT_DIR_BEGIN, directory, GIT_DIR_END];
+
+        let code_meta = &mut self.code_meta;
+        code_meta.fill(0);
+
+        let mut offset = 0;
+        for part in parts.iter() {
+            let end = offset + part.len();
+            if end > code_meta.len() {
+                return Err(CodeMetaTooLong.into());
+            }
+
+            code_meta[offset..end].copy_from_slice(part.as_bytes());
+            offset = end;
+        }
+
+        Ok(())
+    }
+}
+
+// the user account holds a small header followed by a ring buffer of the most recent status updates.
+// The header is the current status code, the current stage, the index the next entry will be written to,
+// the number of entries written so far (capped at STATUS_HISTORY_LEN) and the account's bump seed.  Each entry is its status code,
+// stage, progress, unix timestamp, message length and then the message itself, which is capped at 255 bytes
+// so that the length fits in a u8
+pub const MAX_LOG_MESSAGE_LEN : usize = 255;
+pub const STATUS_HISTORY_LEN : usize = 8;
+
+pub const USER_HEADER_SIZE : usize = std::mem::size_of::<UserHeader>();
+pub const STATUS_ENTRY_SIZE : usize = std::mem::size_of::<StatusEntryData>();
+
+// offset of the current stage in the user account, for filtering accounts by stage
+pub const USER_STAGE_OFFSET : usize = 1;
+pub const USER_BUMP_OFFSET : usize = 4;
+
+// stored in an entry's progress byte when no progress was reported
+pub const NO_PROGRESS : u8 = u8::MAX;
+
+impl UserAccount {
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        let account : &Self = data.get(..get_userdata_size())
+            .and_then(|bytes| bytemuck::try_from_bytes(bytes).ok())
+            .ok_or(InvalidUserData)?;
+
+        if account.header.next_index as usize >= STATUS_HISTORY_LEN || account.header.count as usize > STATUS_HISTORY_LEN {
+            return Err(InvalidUserData.into());
+        }
+
+        Ok(account)
+    }
+
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        let account : &mut Self = data.get_mut(..get_userdata_size())
+            .and_then(|bytes| bytemuck::try_from_bytes_mut(bytes).ok())
+            .ok_or(InvalidUserData)?;
+
+        if account.header.next_index as usize >= STATUS_HISTORY_LEN || account.header.count as usize > STATUS_HISTORY_LEN {
+            return Err(InvalidUserData.into());
+        }
+
+        Ok(account)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StatusEntry {
     pub status_code : u8,
-    pub string_len : u8,
+    pub stage : VerificationStage,
+    pub progress : Option<u8>,
+    pub timestamp : i64,
     pub log_message : String
 }
 
-pub fn get_metadata_size() -> usize {
-    let encoded = ProgramMetaData {test_address : solana_program::system_program::id(), last_verified_slot : 0, verified_code : 0, data_hash : [0 ; 32], code_meta : [0; 512]}
-        .try_to_vec().unwrap();
+impl StatusEntry {
+    /// Reads a single ring buffer entry
+    pub fn unpack(data: &StatusEntryData) -> Result<Self, ProgramError> {
+        let stage = VerificationStage::try_from(data.stage)?;
+        let progress = if data.progress == NO_PROGRESS { None } else { Some(data.progress) };
+
+        let log_message = data.message.get(..data.message_len as usize)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .ok_or(InvalidUserData)?;
 
-    encoded.len()
+        Ok(StatusEntry {
+            status_code : data.status_code,
+            stage,
+            progress,
+            timestamp : i64::from_le_bytes(data.timestamp),
+            log_message : log_message.to_string()
+        })
+    }
+
+    /// Writes a single ring buffer entry, truncating the message to MAX_LOG_MESSAGE_LEN bytes if required
+    pub fn pack(&self, data: &mut StatusEntryData) {
+        let message = truncate_log_message(&self.log_message);
+
+        data.status_code = self.status_code;
+        data.stage = self.stage as u8;
+        data.progress = self.progress.unwrap_or(NO_PROGRESS);
+        data.timestamp = self.timestamp.to_le_bytes();
+        data.message_len = message.len() as u8;
+        data.message[..message.len()].copy_from_slice(message.as_bytes());
+    }
 }
 
-// we will allow log messages up to 256 characters
-pub fn get_userdata_size() -> usize {
-    let size : usize = 2 + 4 * 255;
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UserMetaData {
+    pub status_code : u8,
+    pub stage : VerificationStage,
+    pub bump : u8,
+    // oldest entry first
+    pub history : Vec<StatusEntry>
+}
+
+impl UserMetaData {
+    /// Reads the current status code and the status history out of a user account's data
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let account = UserAccount::load(data)?;
+        let header = &account.header;
+
+        let next_index = header.next_index as usize;
+        let count = header.count as usize;
+
+        let mut history = Vec::with_capacity(count);
+        for i in 0..count {
+            let index = (next_index + STATUS_HISTORY_LEN - count + i) % STATUS_HISTORY_LEN;
+            history.push(StatusEntry::unpack(&account.entries[index])?);
+        }
 
-    return size;
+        Ok(UserMetaData {
+            status_code : header.status_code,
+            stage : VerificationStage::try_from(header.stage)?,
+            bump : header.bump,
+            history
+        })
+    }
+
+    /// The most recent status update, if there has been one
+    pub fn latest(&self) -> Option<&StatusEntry> {
+        self.history.last()
+    }
+
+    /// Clears the status history and stores the account's bump seed
+    pub fn reset(data: &mut [u8], bump : u8) -> ProgramResult {
+        let header : &mut UserHeader = data.get_mut(..USER_HEADER_SIZE)
+            .and_then(|bytes| bytemuck::try_from_bytes_mut(bytes).ok())
+            .ok_or(InvalidUserData)?;
+
+        *header = UserHeader {status_code : 0, stage : 0, next_index : 0, count : 0, bump};
+
+        Ok(())
+    }
+
+    /// Writes a new entry into the ring buffer, overwriting the oldest entry once it is full,
+    /// and sets the current status code and stage to those of the new entry
+    pub fn push(data: &mut [u8], entry: &StatusEntry) -> ProgramResult {
+        let account = UserAccount::load_mut(data)?;
+
+        let next_index = account.header.next_index as usize;
+        entry.pack(&mut account.entries[next_index]);
+
+        let header = &mut account.header;
+        header.status_code = entry.status_code;
+        header.stage = entry.stage as u8;
+        header.next_index = ((next_index + 1) % STATUS_HISTORY_LEN) as u8;
+        header.count = std::cmp::min(header.count as usize + 1, STATUS_HISTORY_LEN) as u8;
+
+        Ok(())
+    }
 }
 
-pub fn get_current_userdata_size(user_data: UserMetaData) -> usize {
+// cut a message down to at most MAX_LOG_MESSAGE_LEN bytes without splitting a utf8 character
+pub fn truncate_log_message(message : &str) -> &str {
+    if message.len() <= MAX_LOG_MESSAGE_LEN {
+        return message;
+    }
+
+    let mut end = MAX_LOG_MESSAGE_LEN;
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
 
-    let encoded = user_data.try_to_vec().unwrap();
+    &message[..end]
+}
 
-    encoded.len()
+pub fn get_metadata_size() -> usize {
+    std::mem::size_of::<ProgramMetaData>()
 }
 
+// header followed by the ring buffer of status entries
+pub fn get_userdata_size() -> usize {
+    std::mem::size_of::<UserAccount>()
+}
 
 security_txt! {
     // Required fields
@@ -62,4 +294,133 @@ security_txt! {
     preferred_languages: "en",
     source_code: "https://github.com/daoplays/sol_verify",
     acknowledgements: "solana-dev discord"
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp : i64) -> StatusEntry {
+        StatusEntry {
+            status_code : timestamp as u8,
+            stage : VerificationStage::Building,
+            progress : Some(10),
+            timestamp,
+            log_message : format!("update {}", timestamp)
+        }
+    }
+
+    fn user_account(updates : i64) -> Vec<u8> {
+        let mut data = vec![0u8; get_userdata_size()];
+        UserMetaData::reset(&mut data, 255).unwrap();
+        for timestamp in 0..updates {
+            UserMetaData::push(&mut data, &entry(timestamp)).unwrap();
+        }
+        data
+    }
+
+    fn timestamps(user : &UserMetaData) -> Vec<i64> {
+        user.history.iter().map(|entry| entry.timestamp).collect()
+    }
+
+    #[test]
+    fn entries_are_kept_oldest_first() {
+        let user = UserMetaData::unpack(&user_account(3)).unwrap();
+        assert_eq!(timestamps(&user), vec![0, 1, 2]);
+        assert_eq!(user.latest(), Some(&entry(2)));
+        assert_eq!((user.status_code, user.stage, user.bump), (2, VerificationStage::Building, 255));
+
+        assert!(UserMetaData::unpack(&user_account(0)).unwrap().history.is_empty());
+    }
+
+    #[test]
+    fn the_oldest_entries_are_overwritten_once_full() {
+        let full = user_account(STATUS_HISTORY_LEN as i64);
+        let user = UserMetaData::unpack(&full).unwrap();
+        assert_eq!(timestamps(&user), (0..8).collect::<Vec<i64>>());
+        assert_eq!((full[2], full[3]), (0, STATUS_HISTORY_LEN as u8));
+
+        let wrapped = user_account(11);
+        assert_eq!((wrapped[2], wrapped[3]), (3, STATUS_HISTORY_LEN as u8));
+        let user = UserMetaData::unpack(&wrapped).unwrap();
+        assert_eq!(timestamps(&user), (3..11).collect::<Vec<i64>>());
+        assert_eq!(user.latest(), Some(&entry(10)));
+    }
+
+    #[test]
+    fn reset_clears_the_history() {
+        let mut data = user_account(11);
+        UserMetaData::reset(&mut data, 7).unwrap();
+        UserMetaData::push(&mut data, &entry(20)).unwrap();
+
+        let user = UserMetaData::unpack(&data).unwrap();
+        assert_eq!(timestamps(&user), vec![20]);
+        assert_eq!(user.bump, 7);
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let mut data = user_account(1);
+        data[2] = STATUS_HISTORY_LEN as u8;
+        assert!(UserMetaData::unpack(&data).is_err());
+        assert!(UserMetaData::push(&mut data, &entry(1)).is_err());
+
+        let mut data = user_account(1);
+        data[3] = STATUS_HISTORY_LEN as u8 + 1;
+        assert!(UserMetaData::unpack(&data).is_err());
+
+        assert!(UserMetaData::unpack(&user_account(1)[..get_userdata_size() - 1]).is_err());
+    }
+
+    #[test]
+    fn long_messages_are_cut_at_a_character() {
+        let short = "é".repeat(MAX_LOG_MESSAGE_LEN / 2);
+        assert_eq!(truncate_log_message(&short), short);
+
+        // 256 bytes, so the limit falls inside the last character
+        let long = "é".repeat(128);
+        let truncated = truncate_log_message(&long);
+        assert_eq!(truncated.len(), MAX_LOG_MESSAGE_LEN - 1);
+        assert_eq!(truncated, "é".repeat(127));
+
+        let mut data = user_account(0);
+        let entry = StatusEntry {log_message : long.clone(), ..entry(0)};
+        UserMetaData::push(&mut data, &entry).unwrap();
+        assert_eq!(UserMetaData::unpack(&data).unwrap().history[0].log_message, truncated);
+    }
+
+    fn code_meta_text(meta : &ProgramMetaData) -> String {
+        String::from_utf8_lossy(&meta.code_meta).trim_end_matches('\0').to_string()
+    }
+
+    #[test]
+    fn code_meta_holds_the_source() {
+        let mut meta : ProgramMetaData = bytemuck::Zeroable::zeroed();
+        meta.set_code_meta("https://github.com/daoplays/sol_verify", "57d41cf", "program").unwrap();
+        assert_eq!(code_meta_text(&meta), [
+            "https://github.com/daoplays/sol_verify", GIT_COMMIT_BEGIN, "57d41cf", GIT_DIR_BEGIN, "program", GIT_DIR_END
+        ].concat().trim_end_matches('\0'));
+
+        // exactly filling code_meta is allowed
+        let markers = GIT_COMMIT_BEGIN.len() + GIT_DIR_BEGIN.len() + GIT_DIR_END.len();
+        let repo = "a".repeat(meta.code_meta.len() - markers);
+        assert!(meta.set_code_meta(&repo, "", "").is_ok());
+    }
+
+    #[test]
+    fn oversized_code_meta_is_rejected() {
+        let mut meta : ProgramMetaData = bytemuck::Zeroable::zeroed();
+        meta.set_code_meta("https://github.com/daoplays/sol_verify", "57d41cf", "program").unwrap();
+
+        let markers = GIT_COMMIT_BEGIN.len() + GIT_DIR_BEGIN.len() + GIT_DIR_END.len();
+        let repo = "a".repeat(meta.code_meta.len() - markers + 1);
+        assert_eq!(meta.set_code_meta(&repo, "", ""), Err(CodeMetaTooLong.into()));
+        assert_eq!(meta.set_code_meta("", "", &repo), Err(CodeMetaTooLong.into()));
+    }
+
+    #[test]
+    fn account_sizes_match_the_layout() {
+        assert_eq!(get_metadata_size(), METADATA_BUMP_OFFSET + 1);
+        assert_eq!(get_userdata_size(), USER_HEADER_SIZE + STATUS_HISTORY_LEN * STATUS_ENTRY_SIZE);
+        assert_eq!(STATUS_ENTRY_SIZE, 12 + MAX_LOG_MESSAGE_LEN);
+    }
+}
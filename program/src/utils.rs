@@ -2,11 +2,46 @@ use solana_program::{
     account_info::AccountInfo,
     entrypoint::ProgramResult,
     msg,
-    program::{invoke_signed},
-    pubkey::Pubkey, rent,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
     native_token::LAMPORTS_PER_SOL
 };
 
+// check that data_account is the program derived address for seed_1 and seed_2, returning its bump seed.
+// Accounts that already exist store their bump at bump_offset, so we can check that with the much cheaper
+// create_program_address and only fall back to searching with find_program_address for new accounts, or
+// accounts that were created before the bump was stored
+pub fn check_program_data_account(
+    data_account: &AccountInfo,
+    program_id :  &Pubkey,
+    seed_1 : &[u8],
+    seed_2 : &[u8],
+    bump_offset : usize
+) -> Result<u8, ProgramError>
+{
+    if data_account.owner == program_id && data_account.data_len() > bump_offset {
+        let bump_seed = data_account.data.borrow()[bump_offset];
+
+        if let Ok(expected_key) = Pubkey::create_program_address(&[seed_1, seed_2, &[bump_seed]], program_id) {
+            if data_account.key == &expected_key {
+                return Ok(bump_seed);
+            }
+        }
+    }
+
+    let (expected_key, bump_seed) = Pubkey::find_program_address(&[seed_1, seed_2], program_id);
+
+    if data_account.key != &expected_key {
+        msg!("expected program derived account {}", expected_key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(bump_seed)
+}
+
 pub fn create_program_data_account<'a>(
     funding_account: &AccountInfo<'a>,
     data_account: &AccountInfo<'a>,
@@ -20,18 +55,12 @@ pub fn create_program_data_account<'a>(
 
     // Check if the account has already been initialized
     if **data_account.try_borrow_lamports()? > 0 {
-        msg!("user's data account is already initialized. skipping");
         return Ok(());
     }
 
-    msg!("Creating user's data account");
-        
-    // the bidders data account just holds a single usize giving their location in the
-    // bid array and a bool
     let space : u64 = data_size.try_into().unwrap();
-    let lamports = rent::Rent::default().minimum_balance(data_size);
+    let lamports = Rent::get()?.minimum_balance(data_size);
 
-    msg!("Require {} lamports for {} size data", lamports, data_size);
     let ix = solana_program::system_instruction::create_account(
         funding_account.key,
         data_account.key,
@@ -51,7 +80,100 @@ pub fn create_program_data_account<'a>(
 }
 
 
+// grow an existing program owned data account if it was created with an older, smaller layout.
+// The funding account tops up the rent and the new space is zeroed
+pub fn resize_program_data_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    data_account: &AccountInfo<'a>,
+    data_size : usize
+) -> ProgramResult
+{
+    if data_account.data_len() >= data_size {
+        return Ok(());
+    }
+
+    msg!("Resizing data account from {} to {}", data_account.data_len(), data_size);
+
+    let required_lamports = Rent::get()?.minimum_balance(data_size);
+    let current_lamports = **data_account.try_borrow_lamports()?;
+
+    if required_lamports > current_lamports {
+        let ix = solana_program::system_instruction::transfer(
+            funding_account.key,
+            data_account.key,
+            required_lamports - current_lamports
+        );
+
+        invoke(
+            &ix,
+            &[funding_account.clone(), data_account.clone()]
+        )?;
+    }
+
+    data_account.realloc(data_size, true)?;
+
+    Ok(())
+}
 
 pub fn to_sol(value : u64) -> f64 {
     (value as f64) / (LAMPORTS_PER_SOL as f64)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUMP_OFFSET : usize = 4;
+
+    fn check(key : &Pubkey, owner : &Pubkey, data : &mut [u8], program_id : &Pubkey, seed : &Pubkey) -> Result<u8, ProgramError> {
+        let mut lamports = 1;
+        let account = AccountInfo::new(key, false, true, &mut lamports, data, owner, false, 0);
+        check_program_data_account(&account, program_id, &seed.to_bytes(), b"user_account", BUMP_OFFSET)
+    }
+
+    #[test]
+    fn the_derived_address_is_accepted() {
+        let program_id = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let (key, bump) = Pubkey::find_program_address(&[&user.to_bytes(), b"user_account"], &program_id);
+
+        // with the bump stored, and for a new account without one
+        let mut data = vec![0, 0, 0, 0, bump];
+        assert_eq!(check(&key, &program_id, &mut data, &program_id, &user), Ok(bump));
+        assert_eq!(check(&key, &solana_program::system_program::id(), &mut [], &program_id, &user), Ok(bump));
+    }
+
+    #[test]
+    fn other_addresses_are_rejected() {
+        let program_id = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let (_, bump) = Pubkey::find_program_address(&[&user.to_bytes(), b"user_account"], &program_id);
+
+        // another user's account
+        let other = Pubkey::find_program_address(&[&Pubkey::new_unique().to_bytes(), b"user_account"], &program_id).0;
+        let mut data = vec![0, 0, 0, 0, bump];
+        assert_eq!(check(&other, &program_id, &mut data, &program_id, &user), Err(ProgramError::InvalidAccountData));
+
+        // the right seeds under another program
+        let foreign = Pubkey::find_program_address(&[&user.to_bytes(), b"user_account"], &Pubkey::new_unique()).0;
+        assert_eq!(check(&foreign, &program_id, &mut data, &program_id, &user), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn bumps_are_only_read_from_program_accounts() {
+        let program_id = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let (key, bump) = Pubkey::find_program_address(&[&user.to_bytes(), b"user_account"], &program_id);
+
+        // an account the program doesn't own, such as one sent lamports before it was created, is still found at the derived
+        // address, with the bump searched for rather than read from its data
+        let mut data = vec![0, 0, 0, 0, bump.wrapping_sub(1)];
+        assert_eq!(check(&key, &Pubkey::new_unique(), &mut data, &program_id, &user), Ok(bump));
+
+        // and neither can a wrong owner make another address pass
+        let other = Pubkey::new_unique();
+        assert_eq!(check(&other, &Pubkey::new_unique(), &mut data, &program_id, &user), Err(ProgramError::InvalidAccountData));
+
+        // a stored bump that doesn't derive the address falls back to the search as well
+        assert_eq!(check(&key, &program_id, &mut data, &program_id, &user), Ok(bump));
+    }
+}
@@ -0,0 +1,335 @@
+// Benchmarks the compute units used by each instruction.  Run with `cargo test --features test-verifier`, under
+// which the program runs natively and only the flow is checked, or `cargo test-bpf --features test-verifier`, under
+// which the built .so is loaded, which is where the numbers are meaningful.  Only the built program can create and
+// resize accounts, so new accounts and accounts without a stored bump seed are only measured under cargo test-bpf,
+// which also sends the same instructions to the program from before the stored bump seeds and the zero copy
+// layouts, built into tests/fixtures by scripts/build-baseline.sh, and compares the two.
+// The test-verifier feature makes the program accept a key generated from TEST_VERIFIER_SEED as the verifier,
+// so update_status and verify_program are always measured.
+
+use {
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        system_program,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        commitment_config::CommitmentLevel,
+        signature::{Keypair, Signer},
+        signer::keypair::keypair_from_seed,
+        transaction::{Transaction, TransactionError},
+    },
+    solana_verify_v05::{
+        accounts,
+        instruction::{Network, StatusMeta, SubmitProgramMeta, VerificationStage, VerifyInstruction, VerifyProgramMeta},
+        processor::Processor,
+        state::{self, ProgramMetaData, UserMetaData},
+    },
+};
+
+// upper bounds for the BPF build, these should only ever go down
+const SUBMIT_PROGRAM_BUDGET : u64 = 60_000;
+const UPDATE_STATUS_BUDGET : u64 = 10_000;
+const VERIFY_PROGRAM_BUDGET : u64 = 20_000;
+
+const TEST_VERIFIER_SEED : [u8; 32] = [7; 32];
+
+// the program from before the stored bump seeds, see scripts/build-baseline.sh
+#[cfg(feature = "test-bpf")]
+const BASELINE_PROGRAM : &str = "solana_verify_v05_before";
+
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[solana_program::account_info::AccountInfo],
+    instruction_data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    Processor::process(program_id, accounts, instruction_data)
+}
+
+// the state of the program owned accounts before the first submit
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Existing {
+    // a first submission, so submit creates both accounts
+    Nothing,
+    // accounts written before the bump seeds were stored, so the program has to search for their addresses
+    WithoutBump,
+    // accounts that store their bump seeds, so the program only has to check their addresses
+    WithBump
+}
+
+// the compute units used by each instruction
+#[derive(Debug, Clone, Copy)]
+struct Units {
+    submit : u64,
+    update : u64,
+    verify : u64
+}
+
+struct Bench {
+    program_id : Pubkey,
+    owner : Keypair,
+    verifier : Keypair,
+    real_address : Pubkey,
+    test_address : Pubkey,
+    metadata_key : Pubkey,
+    metadata_bump : u8,
+    userdata_key : Pubkey,
+    user_bump : u8
+}
+
+impl Bench {
+    fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let owner = Keypair::new();
+        let real_address = Pubkey::new_unique();
+
+        let verifier = keypair_from_seed(&TEST_VERIFIER_SEED).unwrap();
+        assert_eq!(verifier.pubkey(), accounts::get_expected_daoplays_key());
+
+        let (metadata_key, metadata_bump) = Pubkey::find_program_address(&[&real_address.to_bytes(), b"dev_net"], &program_id);
+        let (userdata_key, user_bump) = Pubkey::find_program_address(&[&owner.pubkey().to_bytes(), b"user_account"], &program_id);
+
+        Bench {
+            program_id,
+            owner,
+            verifier,
+            real_address,
+            test_address : Pubkey::new_unique(),
+            metadata_key,
+            metadata_bump,
+            userdata_key,
+            user_bump
+        }
+    }
+
+    // adds the owner's wallet and the program owned accounts in the given state
+    fn add_accounts(&self, mut program_test : ProgramTest, existing : Existing) -> ProgramTest {
+        program_test.add_account(self.owner.pubkey(), Account::new(1_000_000_000, 0, &system_program::id()));
+
+        // the layouts before the bump seeds were added were a byte shorter
+        let (metadata_size, userdata_size) = match existing {
+            Existing::Nothing => return program_test,
+            Existing::WithoutBump => (state::get_metadata_size() - 1, state::get_userdata_size() - 1),
+            Existing::WithBump => (state::get_metadata_size(), state::get_userdata_size())
+        };
+
+        let mut metadata_account = Account::new(1_000_000_000, metadata_size, &self.program_id);
+        let mut user_account = Account::new(1_000_000_000, userdata_size, &self.program_id);
+        if existing == Existing::WithBump {
+            metadata_account.data[state::METADATA_BUMP_OFFSET] = self.metadata_bump;
+            user_account.data[state::USER_BUMP_OFFSET] = self.user_bump;
+        }
+        program_test.add_account(self.metadata_key, metadata_account);
+        program_test.add_account(self.userdata_key, user_account);
+
+        program_test
+    }
+
+    fn submit(&self) -> Instruction {
+        Instruction::new_with_borsh(
+            self.program_id,
+            &VerifyInstruction::SubmitProgram {
+                metadata : SubmitProgramMeta {
+                    address : self.real_address,
+                    network : Network::DevNet,
+                    git_repo : "https://github.com/daoplays/sol_verify".to_string(),
+                    git_commit : "".to_string(),
+                    directory : "program".to_string(),
+                    docker_version : "".to_string(),
+                    rust_version : "1.62".to_string(),
+                    solana_version : "1.10.39".to_string(),
+                    anchor_version : "".to_string()
+                }
+            },
+            vec![
+                AccountMeta::new(self.owner.pubkey(), true),
+                AccountMeta::new(self.metadata_key, false),
+                AccountMeta::new(self.userdata_key, false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ],
+        )
+    }
+
+    fn update_status(&self) -> Instruction {
+        Instruction::new_with_borsh(
+            self.program_id,
+            &VerifyInstruction::UpdateStatus {
+                metadata : StatusMeta {
+                    user_pubkey : self.owner.pubkey(),
+                    status_code : 0,
+                    stage : VerificationStage::Building,
+                    progress : Some(50),
+                    log_message : "building program".to_string()
+                }
+            },
+            vec![
+                AccountMeta::new(self.verifier.pubkey(), true),
+                AccountMeta::new(self.userdata_key, false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ],
+        )
+    }
+
+    fn verify_program(&self) -> Instruction {
+        Instruction::new_with_borsh(
+            self.program_id,
+            &VerifyInstruction::VerifyProgram {
+                metadata : VerifyProgramMeta {
+                    verified_code : 3,
+                    real_address : self.real_address,
+                    test_address : self.test_address,
+                    data_hash : [1; 32],
+                    verified_slot : 1,
+                    network : Network::DevNet,
+                    git_repo : "https://github.com/daoplays/sol_verify".to_string(),
+                    git_commit : "".to_string(),
+                    directory : "program".to_string()
+                }
+            },
+            vec![
+                AccountMeta::new(self.verifier.pubkey(), true),
+                AccountMeta::new(self.metadata_key, false),
+                AccountMeta::new_readonly(self.real_address, false),
+                AccountMeta::new_readonly(self.test_address, false)
+            ],
+        )
+    }
+
+    // sends submit, update_status and verify_program in turn, returning the compute units each used
+    async fn run(&self, banks_client : &mut BanksClient, payer : &Keypair) -> Units {
+        Units {
+            submit : process_with_units(banks_client, payer, &self.owner, &self.program_id, self.submit()).await,
+            update : process_with_units(banks_client, payer, &self.verifier, &self.program_id, self.update_status()).await,
+            verify : process_with_units(banks_client, payer, &self.verifier, &self.program_id, self.verify_program()).await
+        }
+    }
+}
+
+// the banks server only returns simulation details for transactions that fail, so to read the compute
+// units used by an instruction we simulate it followed by an instruction the program always rejects
+async fn simulate_units(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    signer: &Keypair,
+    program_id: &Pubkey,
+    instruction: Option<Instruction>,
+) -> u64 {
+    let signers : Vec<&Keypair> = if instruction.is_some() { vec![payer, signer] } else { vec![payer] };
+
+    let mut instructions : Vec<Instruction> = instruction.into_iter().collect();
+    instructions.push(Instruction::new_with_bytes(*program_id, &[u8::MAX], vec![]));
+
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &signers,
+        recent_blockhash,
+    );
+
+    let result = banks_client
+        .process_transaction_with_preflight_and_commitment_and_context(
+            tarpc::context::current(),
+            transaction,
+            CommitmentLevel::Processed,
+        )
+        .await
+        .unwrap();
+
+    let details = result.simulation_details.unwrap();
+    let failed_index = (instructions.len() - 1) as u8;
+    assert!(
+        matches!(result.result, Some(Err(TransactionError::InstructionError(index, _))) if index == failed_index),
+        "transaction failed early: {:?}", details.logs
+    );
+
+    details.units_consumed
+}
+
+// sends a single instruction and returns the compute units it consumed
+async fn process_with_units(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    signer: &Keypair,
+    program_id: &Pubkey,
+    instruction: Instruction,
+) -> u64 {
+    let baseline = simulate_units(banks_client, payer, signer, program_id, None).await;
+    let total = simulate_units(banks_client, payer, signer, program_id, Some(instruction.clone())).await;
+
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[payer, signer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    total - baseline
+}
+
+// runs the current program against accounts in the given state, checking the accounts it leaves behind
+async fn measure_current(existing : Existing) -> Units {
+    let bench = Bench::new();
+    let (mut banks_client, payer, _recent_blockhash) = bench.add_accounts(ProgramTest::new("solana_verify_v05", bench.program_id, processor!(process_instruction)), existing).start().await;
+
+    let units = bench.run(&mut banks_client, &payer).await;
+
+    let metadata_account = banks_client.get_account(bench.metadata_key).await.unwrap().unwrap();
+    assert_eq!(metadata_account.data.len(), state::get_metadata_size());
+    let metadata = ProgramMetaData::load(&metadata_account.data).unwrap();
+    assert_eq!(metadata.verified_code, 3);
+    assert_eq!(metadata.bump, bench.metadata_bump);
+
+    let user_account = banks_client.get_account(bench.userdata_key).await.unwrap().unwrap();
+    let user_data = UserMetaData::unpack(&user_account.data).unwrap();
+    assert_eq!(user_data.bump, bench.user_bump);
+    assert_eq!(user_data.history.len(), 2);
+    assert_eq!(user_data.stage, VerificationStage::Building);
+
+    units
+}
+
+fn print_units(label : &str, units : &Units) {
+    println!("{:<40} submit_program {:>7}  update_status {:>7}  verify_program {:>7}", label, units.submit, units.update, units.verify);
+}
+
+#[tokio::test]
+async fn test_compute_units() {
+    let mut runs = vec![("accounts with a stored bump seed", measure_current(Existing::WithBump).await)];
+
+    // the native runtime can't create or resize accounts, so the paths that search for the addresses only run on the built program
+    if cfg!(feature = "test-bpf") {
+        runs.push(("accounts without a stored bump seed", measure_current(Existing::WithoutBump).await));
+        runs.push(("new accounts", measure_current(Existing::Nothing).await));
+    }
+
+    for (label, units) in runs.iter() {
+        print_units(label, units);
+        assert!(units.submit <= SUBMIT_PROGRAM_BUDGET);
+        assert!(units.update <= UPDATE_STATUS_BUDGET);
+        assert!(units.verify <= VERIFY_PROGRAM_BUDGET);
+    }
+}
+
+// compares the current program with the baseline, which derived every address with find_program_address and
+// stored records with borsh, on the accounts each would find after a user's first submission
+#[cfg(feature = "test-bpf")]
+#[tokio::test]
+async fn test_compute_units_against_baseline() {
+    let bench = Bench::new();
+    let (mut banks_client, payer, _recent_blockhash) = bench.add_accounts(ProgramTest::new(BASELINE_PROGRAM, bench.program_id, None), Existing::WithoutBump).start().await;
+    let before = bench.run(&mut banks_client, &payer).await;
+
+    let after = measure_current(Existing::WithBump).await;
+
+    print_units("before", &before);
+    print_units("after", &after);
+
+    assert!(after.submit < before.submit);
+    assert!(after.update < before.update);
+    assert!(after.verify < before.verify);
+}
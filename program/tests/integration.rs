@@ -1,36 +1,60 @@
 #![cfg(feature = "test-bpf")]
 
 use {
-    assert_matches::*,
     solana_program::{
         instruction::{AccountMeta, Instruction},
         pubkey::Pubkey,
+        system_program,
     },
+    solana_program_test::*,
     solana_sdk::{signature::Signer, transaction::Transaction},
-    solana_validator::test_validator::*,
+    solana_verify_v05::{
+        instruction::{Network, SubmitProgramMeta, VerifyInstruction},
+        state::{self, UserMetaData},
+    },
 };
 
-#[test]
-fn test_validator_transaction() {
-    solana_logger::setup_with_default("solana_program_runtime=debug");
+// submits a program to the built program, which has to create both accounts
+#[tokio::test]
+async fn test_submit_program() {
     let program_id = Pubkey::new_unique();
 
-    let (test_validator, payer) = TestValidatorGenesis::default()
-        .add_program("bpf_program_template", program_id)
-        .start();
-    let rpc_client = test_validator.get_rpc_client();
+    let (mut banks_client, payer, recent_blockhash) = ProgramTest::new("solana_verify_v05", program_id, None).start().await;
 
-    let blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let address = Pubkey::new_unique();
+    let (metadata_key, _) = Pubkey::find_program_address(&[&address.to_bytes(), b"dev_net"], &program_id);
+    let (userdata_key, _) = Pubkey::find_program_address(&[&payer.pubkey().to_bytes(), b"user_account"], &program_id);
 
-    let mut transaction = Transaction::new_with_payer(
-        &[Instruction {
-            program_id,
-            accounts: vec![AccountMeta::new(payer.pubkey(), false)],
-            data: vec![1, 2, 3],
-        }],
-        Some(&payer.pubkey()),
+    let submit = Instruction::new_with_borsh(
+        program_id,
+        &VerifyInstruction::SubmitProgram {
+            metadata : SubmitProgramMeta {
+                address,
+                network : Network::DevNet,
+                git_repo : "https://github.com/daoplays/sol_verify".to_string(),
+                git_commit : "".to_string(),
+                directory : "program".to_string(),
+                docker_version : "".to_string(),
+                rust_version : "1.62".to_string(),
+                solana_version : "1.10.39".to_string(),
+                anchor_version : "".to_string()
+            }
+        },
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(metadata_key, false),
+            AccountMeta::new(userdata_key, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
     );
-    transaction.sign(&[&payer], blockhash);
 
-    assert_matches!(rpc_client.send_and_confirm_transaction(&transaction), Ok(_));
+    let transaction = Transaction::new_signed_with_payer(&[submit], Some(&payer.pubkey()), &[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let metadata_account = banks_client.get_account(metadata_key).await.unwrap().unwrap();
+    assert_eq!(metadata_account.data.len(), state::get_metadata_size());
+
+    let user_account = banks_client.get_account(userdata_key).await.unwrap().unwrap();
+    let user_data = UserMetaData::unpack(&user_account.data).unwrap();
+    assert_eq!(user_data.history.len(), 1);
 }
@@ -17,4 +17,14 @@ BAD_MAKEFILE = 108
 PREVIOUSLY_VERIFIED_IMMUTABLE = 109
 PREVIOUSLY_VERIFIED_MUTABLE = 110
 EXTRACT_FAILED = 111
-SECURITY_TXT_MISMATCH = 112
\ No newline at end of file
+SECURITY_TXT_MISMATCH = 112
+SOURCE_REVISION_MISMATCH = 113
+
+# verification stages, these match VerificationStage in program/src/instruction.rs
+QUEUED = 0
+PREPARING_ENVIRONMENT = 1
+FETCHING = 2
+BUILDING = 3
+DEPLOYING = 4
+COMPARING = 5
+FINALIZED = 6
@@ -63,18 +63,18 @@ while(True):
                     if (not write_docker_file(dev_client, user_pubkey, args, docker_count)):
                         continue
 
-                    update_idx = get_update_state_idx(user_pubkey, 0, "Program " + program_string + " : creating docker container")
+                    update_idx = get_update_state_idx(user_pubkey, 0, "Program " + program_string + " : creating docker container", PREPARING_ENVIRONMENT, 10)
                     send_transaction(dev_client, [update_idx])
 
                     try:
                         subprocess.run("../docker/build.sh " + str(docker_count), shell=True, check=True)
                     except:
                         log_error("build failed!")
-                        update_idx = get_update_state_idx(user_pubkey, DOCKER_BUILD_FAILED, "Program " + program_string + " : docker build failed to complete, check version numbers are valid")
+                        update_idx = get_update_state_idx(user_pubkey, DOCKER_BUILD_FAILED, "Program " + program_string + " : docker build failed to complete, check version numbers are valid", PREPARING_ENVIRONMENT)
                         send_transaction(dev_client, [update_idx])
                         continue
 
-                    update_idx = get_update_state_idx(user_pubkey, 0, "Program " + program_string + " : clone and build sol_verify repo")
+                    update_idx = get_update_state_idx(user_pubkey, 0, "Program " + program_string + " : clone and build sol_verify repo", PREPARING_ENVIRONMENT, 20)
                     send_transaction(dev_client, [update_idx])
                     time.sleep(5)
                     subprocess.run(["../docker/run.sh " + str(docker_count)], shell=True)
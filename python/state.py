@@ -1,4 +1,4 @@
-from borsh_construct import Enum, CStruct, String, U64, U8
+from borsh_construct import Enum, CStruct, String, U64, U8, Option
 import base58
 from solana import message
 
@@ -51,12 +51,15 @@ VerifyProgramMeta = CStruct(
     "last_verified_slot" / U64,
     "verified" / U8,
     "data_hash" / U8[32],
-    "code_meta" / U8[512]
+    "code_meta" / U8[512],
+    "bump" / U8
 )
 
 StatusArgs = CStruct(
     "address" / U8[32],
     "status_code" / U8,
+    "stage" / U8,  # VerificationStage index, see codes.py
+    "progress" / Option(U8),
     "log_message" / String
 )
 
@@ -61,17 +61,21 @@ def check_args(dev_client, user_pubkey, args):
     program_string = (base58.b58encode(bytearray(args.address))).decode("utf-8")
 
     if (not check_address_exists(dev_client, args.address)):
-        update_idx = get_update_state_idx(user_pubkey, PROGRAM_DOESNT_EXIST, "Program " + program_string + " : address '" + program_string + "' does not exist or has no lamports")
+        update_idx = get_update_state_idx(user_pubkey, PROGRAM_DOESNT_EXIST, "Program " + program_string + " : address '" + program_string + "' does not exist or has no lamports", FETCHING)
         send_transaction(dev_client, [update_idx])
         return False, None, None
 
     # check if the program is using security.txt
-    source_code, upgradeable = check_security(program_string)
+    source_code, upgradeable, declared_revision = check_security(program_string)
 
     if source_code != None and source_code not in args.git_repo:
-        update_idx = get_update_state_idx(user_pubkey, SECURITY_TXT_MISMATCH, "Program " + program_string + " : source code  '" + source_code + "' in security.txt differs from " + args.git_repo)
+        update_idx = get_update_state_idx(user_pubkey, SECURITY_TXT_MISMATCH, "Program " + program_string + " : source code  '" + source_code + "' in security.txt differs from " + args.git_repo, FETCHING)
         send_transaction(dev_client, [update_idx])
         return False, source_code, upgradeable
+
+    # build from the revision declared in security.txt, this updates args.git_repo, args.git_commit and args.directory
+    if declared_revision != None and not pin_source(dev_client, user_pubkey, args):
+        return False, source_code, upgradeable
         
 
     # if we have a git commit check the git address is valid
@@ -79,7 +83,7 @@ def check_args(dev_client, user_pubkey, args):
         try:
             remote = lsremote(args.git_repo)
         except:
-            update_idx = get_update_state_idx(user_pubkey, GIT_REPO_DOESNT_EXIST, "Program " + program_string + " : git repo '" + args.git_repo + "' does not exist or inaccessible")
+            update_idx = get_update_state_idx(user_pubkey, GIT_REPO_DOESNT_EXIST, "Program " + program_string + " : git repo '" + args.git_repo + "' does not exist or inaccessible", FETCHING)
             send_transaction(dev_client, [update_idx])
             return False, source_code, upgradeable
     
@@ -90,7 +94,7 @@ def check_args(dev_client, user_pubkey, args):
     if check_wget(args.git_repo):
         return True, source_code, upgradeable
 
-    update_idx = get_update_state_idx(user_pubkey, GIT_REPO_DOESNT_EXIST, "Program " + program_string + " : archive '" + args.git_repo + "' does not exist or inaccessible using wget")
+    update_idx = get_update_state_idx(user_pubkey, GIT_REPO_DOESNT_EXIST, "Program " + program_string + " : archive '" + args.git_repo + "' does not exist or inaccessible using wget", FETCHING)
     send_transaction(dev_client, [update_idx])
     return False, source_code, upgradeable
 
@@ -99,18 +103,56 @@ def check_security(program_address):
     os.chdir('../client')
 
     source_code = None
-    subprocess.run(["cargo run dummy write_security " + program_address], shell=True)
-    meta_json = json.load(open("program_meta_data.json"))
+    declared_revision = None
+    result = subprocess.run(["cargo run -q -- --url devnet --output json security-txt " + program_address], shell=True, capture_output=True, text=True)
+    meta_json = json.loads(result.stdout)
     print(meta_json)
-    subprocess.run(["rm program_meta_data.json"], shell=True)
+
+    os.chdir(cwd)
+
+    # without a report assume the program can still be upgraded
+    if "error" in meta_json:
+        log_error("security.txt check failed: " + meta_json["error"])
+        return source_code, True, declared_revision
 
     upgradeable =  meta_json["upgradeable"]
-    if (meta_json["source_code"] != "none"):
+    if (meta_json["source_code"] != None):
         source_code = meta_json["source_code"]
 
+    # a commit in source_revision is preferred over a release tag
+    for field in ["source_revision", "source_release"]:
+        if declared_revision == None and meta_json.get(field):
+            declared_revision = meta_json[field].strip()
+
+    return source_code, upgradeable, declared_revision
+
+# the client resolves the revision declared in security.txt the same way verify checks it, and gives the repo,
+# commit and directory to build from, dropping an archive's top level folder if it falls back to the security.txt repo
+def pin_source(dev_client, user_pubkey, args):
+
+    program_string = (base58.b58encode(bytearray(args.address))).decode("utf-8")
+
+    cwd = os.getcwd()
+    os.chdir('../client')
+
+    command = ["cargo", "run", "-q", "--", "--url", "devnet", "--output", "json", "pin-source", program_string, args.git_repo, "--git-commit", args.git_commit, "--directory", args.directory]
+    result = subprocess.run(command, capture_output=True, text=True)
+    pinned = json.loads(result.stdout)
+    print(pinned)
+
     os.chdir(cwd)
 
-    return source_code, upgradeable
+    if "error" in pinned:
+        status_code = pinned["status_code"] if pinned.get("status_code") != None else GIT_REPO_DOESNT_EXIST
+        update_idx = get_update_state_idx(user_pubkey, status_code, "Program " + program_string + " : " + pinned["error"], FETCHING)
+        send_transaction(dev_client, [update_idx])
+        return False
+
+    args.git_repo = pinned["git_repo"]
+    args.git_commit = pinned["git_commit"]
+    args.directory = pinned["directory"]
+
+    return True
 
 
 
@@ -129,7 +171,7 @@ def write_docker_file(dev_client, user_pubkey, args, docker_count):
 
             log_error("dockerfile " + str(args.docker_version) + " not found in map")
 
-            update_idx = get_update_state_idx(user_pubkey, DOCKER_DOESNT_EXIST, "Program " + program_string + " : unknown docker " + args.docker_version)
+            update_idx = get_update_state_idx(user_pubkey, DOCKER_DOESNT_EXIST, "Program " + program_string + " : unknown docker " + args.docker_version, PREPARING_ENVIRONMENT)
             send_transaction(dev_client, [update_idx])
 
             return False
@@ -154,6 +196,11 @@ def write_docker_file(dev_client, user_pubkey, args, docker_count):
 
     return True
 
+# the client run in the docker is built from the same commit as this pipeline, so it accepts the commands written for it
+def pipeline_revision():
+
+    return git.Repo(os.path.dirname(os.path.abspath(__file__)), search_parent_directories=True).head.object.hexsha
+
 def write_config_file(args, user_pubkey, docker_count, upgradeable):
 
     program_string = (base58.b58encode(bytearray(args.address))).decode("utf-8")
@@ -170,14 +217,14 @@ def write_config_file(args, user_pubkey, docker_count, upgradeable):
         f.write("cpanm Archive::Extract\n")
         f.write("cpanm Archive::Zip\n")
         f.write("wget " + args.git_repo + "\n")
-        f.write("if [ ! $(perl extract.perl " + fname + ") ]; then cd /sol_verify/client; cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 111 \"Program " + program_string + " : archive " + fname + " failed to extract\"; exit 1; fi\n")
+        f.write("if [ ! $(perl extract.perl " + fname + ") ]; then cd /sol_verify/client; cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 111 \"Program " + program_string + " : archive " + fname + " failed to extract\" --stage fetching; exit 1; fi\n")
 
 
     f.write("git clone https://github.com/daoplays/sol_verify.git\n")
 
     f.write("cd /sol_verify/client\n")
-    f.write("git checkout 34e38e561959e5d3270f7f9fc1518764f2763ed1\n")
-    f.write("cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 0 'Program " + program_string + " : sol_verify built, airdropping funds'\n")
+    f.write("git checkout " + pipeline_revision() + "\n")
+    f.write("cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 0 'Program " + program_string + " : sol_verify built, airdropping funds' --stage preparing_environment --progress 30\n")
 
     # to avoid rate limits create a new pubkey, airdrop to there and then transfer over
     f.write("solana-keygen new -o temp.json --no-bip39-passphrase\n")
@@ -186,25 +233,27 @@ def write_config_file(args, user_pubkey, docker_count, upgradeable):
     f.write("solana transfer --from temp.json /root/.config/solana/id.json 3.99\n")
 
     # check the provided directory exists
-    f.write("[ ! -d \"/test_repo/" + args.directory + "\" ] && cd /sol_verify/client && cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 102 \"Program " + program_string + " : directory " + args.directory + " doesn't exist in repo\" && exit 1\n")
+    f.write("[ ! -d \"/test_repo/" + args.directory + "\" ] && cd /sol_verify/client && cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 102 \"Program " + program_string + " : directory " + args.directory + " doesn't exist in repo\" --stage fetching && exit 1\n")
   
 
-    f.write("cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 0 'Program " + program_string + " : cloning program repo and building program'\n")
+    f.write("cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 0 'Program " + program_string + " : cloning program repo and building program' --stage fetching --progress 40\n")
 
     f.write("cd /test_repo\n")
 
     #check the commit given is valid
     if args.git_commit != "":
-        f.write("if ! git cat-file -e " + args.git_commit + "^{commit}; then cd /sol_verify/client; cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 103 \"Program " + program_string + " : commit " + args.git_commit + " doesn't exist in repo\"; exit 1; fi\n")
+        f.write("if ! git cat-file -e " + args.git_commit + "^{commit}; then cd /sol_verify/client; cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 103 \"Program " + program_string + " : commit " + args.git_commit + " doesn't exist in repo\" --stage fetching; exit 1; fi\n")
 
         f.write("git checkout " + args.git_commit + "\n")
 
     f.write("cd /test_repo/" + args.directory + "\n")
 
     # if neither Cargo.toml or makefile exist in the current directory we can't do anything
-    f.write("if [ ! -f Cargo.toml ] && [ ! -f makefile ]; then cd /sol_verify/client; cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 104 \"Program " + program_string + " : Neither makefile nor Cargo.toml found to build project in " + args.directory + "\"; exit 1; fi\n")
+    f.write("if [ ! -f Cargo.toml ] && [ ! -f makefile ]; then cd /sol_verify/client; cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 104 \"Program " + program_string + " : Neither makefile nor Cargo.toml found to build project in " + args.directory + "\" --stage building; exit 1; fi\n")
 
 
+    f.write("cd /sol_verify/client; cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 0 'Program " + program_string + " : building program' --stage building --progress 50; cd /test_repo/" + args.directory + "\n")
+
     # a rust project will produce the .so in target/deploy, so set that as the default
     f.write("export OUTDIR=\"target/deploy\"\n")
 
@@ -212,20 +261,22 @@ def write_config_file(args, user_pubkey, docker_count, upgradeable):
     f.write("if [ -f Cargo.toml ]; then cargo build-bpf --generate-child-script-on-failure; fi\n")
 
     # check if the build failed
-    f.write("if [ -f cargo-build-sbf-child-script-cargo.sh ]; then cd /sol_verify/client; cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 105 \"Program " + program_string + " : cargo build-sbf failed\"; exit 1; fi\n")
+    f.write("if [ -f cargo-build-sbf-child-script-cargo.sh ]; then cd /sol_verify/client; cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 105 \"Program " + program_string + " : cargo build-sbf failed\" --stage building; exit 1; fi\n")
 
     # if this is a c program then makefile exists, and we will need to get OUTDIR from the makefile
     f.write("if [ -f makefile ]; then make; export OUTDIR=\"$(grep OUT_DIR makefile  | sed 's:.*=::')\"; fi\n")
 
     # if OUTDIR is empty now then it didn't exist in the makefile, so throw an error
-    f.write("if [ -z \"${OUTDIR}\" ]; then cd /sol_verify/client; cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 108 \"Program " + program_string + " : OUT_DIR not present in makefile\"; exit 1; fi\n")
+    f.write("if [ -z \"${OUTDIR}\" ]; then cd /sol_verify/client; cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 108 \"Program " + program_string + " : OUT_DIR not present in makefile\" --stage building; exit 1; fi\n")
 
     # as a last sanity check make sure that the .so is there
-    f.write("if [ ! -f $OUTDIR/*.so ]; then cd /sol_verify/client; cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 105 \"Program " + program_string + " : build failed to produce .so in $OUTDIR\"; exit 1; fi\n")
+    f.write("if [ ! -f $OUTDIR/*.so ]; then cd /sol_verify/client; cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 105 \"Program " + program_string + " : build failed to produce .so in $OUTDIR\" --stage building; exit 1; fi\n")
 
     # in case OUTDIR was specified as a relative path, get the absolute path
     f.write("export ABSDIR=$(realpath $OUTDIR)\n")
 
+    f.write("cd /sol_verify/client; cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 0 'Program " + program_string + " : deploying program' --stage deploying --progress 70; cd /test_repo/" + args.directory + "\n")
+
     # deploy the program
     if upgradeable:
         f.write("solana program deploy $OUTDIR/*.so --commitment finalized\n")
@@ -234,7 +285,7 @@ def write_config_file(args, user_pubkey, docker_count, upgradeable):
 
     f.write("cd /sol_verify/client\n")
 
-    f.write("cargo run /root/.config/solana/id.json update_status " + user_pubkey + " 0 'Program " + program_string + " : running verification'\n")
+    f.write("cargo run -- -k /root/.config/solana/id.json update-status " + user_pubkey + " 0 'Program " + program_string + " : running verification' --stage comparing --progress 85\n")
 
     f.write("sleep 30\n")
     
@@ -245,7 +296,7 @@ def write_config_file(args, user_pubkey, docker_count, upgradeable):
         f.write("COMMIT=" + args.git_commit + "\n")
 
 
-    f.write("cargo run /root/.config/solana/id.json verify $ABSDIR/*-keypair.json " + program_string + " " + str(network_to_u8(args.network)) + " " + user_pubkey + " " + args.git_repo + " $COMMIT " + args.directory + "\n")
+    f.write("cargo run -- -k /root/.config/solana/id.json verify $ABSDIR/*-keypair.json " + program_string + " " + str(network_to_u8(args.network)) + " " + user_pubkey + " " + args.git_repo + " $COMMIT " + args.directory + "\n")
 
     f.close()
     
@@ -333,7 +384,8 @@ def check_user_status_code(dev_client, user_account_key):
     return status_code
 
 
-def get_update_state_idx(user_account_key, status_code, log_message):
+# stage is where the verification has got to, so a failure is reported at the stage it happened in
+def get_update_state_idx(user_account_key, status_code, log_message, stage, progress = None):
 
     config = load_config("config.json")
     wallet = load_key(config["wallet"])
@@ -344,7 +396,7 @@ def get_update_state_idx(user_account_key, status_code, log_message):
 
     instruction = TransactionInstruction(
         program_id = PublicKey(PROGRAM_KEY),
-        data = Verifier_Instructions.build(Verifier_Instructions.enum.UpdateStatus(bytes(PublicKey(user_account_key)), status_code, log_message)),
+        data = Verifier_Instructions.build(Verifier_Instructions.enum.UpdateStatus(bytes(PublicKey(user_account_key)), status_code, stage, progress, log_message)),
         keys = [
             AccountMeta(pubkey=wallet.public_key, is_signer=True, is_writable=True),
             AccountMeta(pubkey=user_account, is_signer=False, is_writable=True),